@@ -1,9 +1,9 @@
 use shorai::{
     geometry::Line,
     missile::{Missile, MissileSet},
-    pathfind,
+    pathfind::{Budget, Event, Observer, SearchContext, Stats},
     pos::Pos,
-    FxIndexMap,
+    Cost, FxIndexMap,
 };
 use std::{iter::once, time::Instant};
 use structopt::StructOpt;
@@ -37,7 +37,7 @@ struct Args {
     #[structopt(long, default_value = "10.0")]
     max_time: f32,
 
-    #[structopt(long, default_value = "10000", help = "Custom limit to prevent deadlocks")]
+    #[structopt(long, default_value = "10000", help = "Maximum number of nodes to expand")]
     max_steps: usize,
 
     #[structopt(long, default_value = "50.0", help = "Size of each 'grid cell'")]
@@ -48,6 +48,31 @@ struct Args {
 
     #[structopt(long, default_value = "325.0", help = "Player movement speed")]
     move_speed: f32,
+
+    #[structopt(long, help = "Render the explored search tree to out/search.png")]
+    draw_search: bool,
+
+    #[structopt(long, help = "Print every search event")]
+    log_search: bool,
+}
+
+/// Collects the explored search tree, and optionally logs every search event as it happens.
+#[derive(Default)]
+struct Explored {
+    log: bool,
+    edges: Vec<Line>,
+}
+
+impl Observer<Pos, Cost> for Explored {
+    fn observe(&mut self, event: Event<Pos, Cost>) {
+        if self.log {
+            println!("{:?}", event);
+        }
+
+        if let Event::Expanded { node, parent: Some(parent) } = event {
+            self.edges.push(Line(parent.vec(), node.vec()));
+        }
+    }
 }
 
 fn main() {
@@ -87,37 +112,73 @@ fn main() {
     let mis = MissileSet(missiles);
 
     println!("searching");
-    let mut steps = max_steps;
+    let budget = Budget { max_expansions: Some(max_steps), ..Budget::default() };
 
     let now = Instant::now();
 
-    let result = pathfind::find(
+    let explored = Explored { log: args.log_search, edges: Vec::new() };
+    let mut ctx = SearchContext::with_observer((Stats::default(), explored));
+    let path = ctx.find_partial(
         origin,
+        &budget,
         |pos| pos.successors(step_time, step_size).into_iter(),
         |beg, end| mis.collides_points(beg, end, move_speed, pawn_size).is_none(),
         |beg, end| (beg.dist(end) / step_size).into(),
         |pos| pos.dist_sq(&target).into(),
-        |pos| {
-            steps -= 1;
-            steps == 0 || max_time <= pos.time() || pos.is_same_pos(&target, step_size)
-        },
+        |pos| max_time <= pos.time() || pos.is_same_pos(&target, step_size),
         |n1, n2, to_node| Pos::jump_calc(n1, n2, to_node, move_speed),
+        // If we can't get anywhere, we at least want to survive for as long as possible.
+        |pos| (-pos.time()).into(),
     );
 
     let elapsed = now.elapsed();
 
-    let (steps, time, score) = match &result {
-        Some((v, p)) => (max_steps - steps, v.last().unwrap().time() - curr_time, p.0),
-        None => (steps, 0.0, 0.0),
-    };
+    let time = path.nodes.last().unwrap().time() - curr_time;
 
     #[rustfmt::skip]
-    println!("search took {:?} - score: {}, steps: {}/{}, seconds: {}/{}", elapsed, score, steps, max_steps, time, max_time - curr_time);
+    println!("search took {:?} - score: {}, partial: {}, seconds: {}/{}", elapsed, path.cost, path.partial, time, max_time - curr_time);
+    let (stats, explored) = ctx.observer();
+    println!("{:#?}", stats);
 
-    match result {
-        Some((path, _cost)) => render_path(render_smear, render_scale, &mis, &path, move_speed, pawn_size, render_step),
-        None => println!("No path found!"),
+    match path.nodes.len() {
+        1 => println!("No path found!"),
+        _ => render_path(render_smear, render_scale, &mis, &path.nodes, move_speed, pawn_size, render_step),
     }
+
+    if args.draw_search {
+        render_search(render_scale, &explored.edges, &path.nodes);
+    }
+}
+
+fn render_search(scale: f32, edges: &[Line], path: &[Pos]) {
+    let points = || edges.iter().flat_map(|l| [l.0, l.1]).chain(path.iter().map(Pos::vec));
+
+    let (min_x, min_y) = points().fold((0.0_f32, 0.0_f32), |(x, y), p| (x.min(p.x), y.min(p.y)));
+    let (max_x, max_y) = points().fold((SIZE.x(), SIZE.y()), |(x, y), p| (x.max(p.x), y.max(p.y)));
+
+    let (px_size_x, px_size_y) = (((max_x - min_x) * scale) as u32 + 1, ((max_y - min_y) * scale) as u32 + 1);
+    let mut image = RgbaImage::from_pixel(px_size_x, px_size_y, Rgba([0, 0, 0, 255]));
+
+    let mut draw = |line: Line, color: Rgba<u8>| {
+        let (from, into) = ((line.0 - Vec2::new(min_x, min_y)) * scale, (line.1 - Vec2::new(min_x, min_y)) * scale);
+        let steps = (into - from).mag().ceil().max(1.0) as u32;
+
+        for i in 0..=steps {
+            let p = from + (into - from) * (i as f32 / steps as f32);
+            image.blend_pixel(p.x as u32, p.y as u32, color);
+        }
+    };
+
+    for &edge in edges {
+        draw(edge, Rgba([100, 100, 255, 60]));
+    }
+
+    for window in path.windows(2) {
+        draw(Line(window[0].vec(), window[1].vec()), Rgba([255, 255, 255, 255]));
+    }
+
+    let _ = std::fs::create_dir("out");
+    image.save("out/search.png").unwrap();
 }
 
 fn render_path(smear: f32, scale: f32, mis: &MissileSet, path: &[Pos], move_speed: f32, pawn_size: f32, step: f32) {
@@ -3,7 +3,7 @@ use rand::{prelude::StdRng, SeedableRng};
 
 use shorai::{
     missile::{Missile, MissileSet},
-    pathfind,
+    pathfind::{self, SearchContext},
     pos::Pos,
     FxIndexMap,
 };
@@ -43,22 +43,25 @@ fn generic_path_benchmark(c: &mut Criterion) {
 
     let mis = MissileSet(missiles);
 
+    let successors = |pos: &Pos| pos.successors(step_time, step_size);
+    let is_valid_move = |beg: &Pos, end: &Pos| mis.collides_points(beg, end, move_speed, pawn_size).is_none();
+    let movement_cost = |beg: &Pos, end: &Pos| (beg.dist(end) / step_size).into();
+    let heuristic = |pos: &Pos| pos.dist_sq(&target).into();
+    let success = |pos: &Pos| pos.is_same_pos(&target, step_size) || max_time <= pos.time();
+    let jump_check = |beg: &Pos, _: &Pos, &end: &Pos| {
+        let mut end = end;
+        end.t = beg.t + beg.dist(&end) / move_speed;
+        Some(end)
+    };
+
     c.bench_function("find_path", |b| {
-        b.iter(|| {
-            pathfind::find(
-                origin,
-                |pos| pos.successors(step_time, step_size),
-                |beg, end| mis.collides_points(beg, end, move_speed, pawn_size).is_none(),
-                |beg, end| (beg.dist(end) / step_size).into(),
-                |pos| pos.dist_sq(&target).into(),
-                |pos| pos.is_same_pos(&target, step_size) || max_time <= pos.time(),
-                |beg, _, &end| {
-                    let mut end = end;
-                    end.t = beg.t + beg.dist(&end) / move_speed;
-                    Some(end)
-                },
-            )
-        });
+        b.iter(|| pathfind::find(origin, successors, is_valid_move, movement_cost, heuristic, success, jump_check));
+    });
+
+    // Same query as above, but reusing the buffers between runs instead of allocating new ones.
+    let mut ctx = SearchContext::new();
+    c.bench_function("find_path_reused_context", |b| {
+        b.iter(|| ctx.find(origin, successors, is_valid_move, movement_cost, heuristic, success, jump_check));
     });
 }
 
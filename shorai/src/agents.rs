@@ -0,0 +1,618 @@
+//! Planning paths for several pawns that mustn't run into each other.
+//!
+//! Since every [`Pos`] carries the time it's reached at, a planned path describes exactly where a
+//! pawn is at any point in time. That makes it possible to treat the paths of other pawns as moving
+//! obstacles, just like missiles. [`plan_prioritized`] plans the agents one by one in priority
+//! order, and records every path it finds in a [`ReservationTable`] that the agents planned after it
+//! have to avoid on top of the [`MissileSet`].
+//!
+//! A pawn is assumed to move in a straight line at its movement speed between the nodes of its path,
+//! and to wait wherever it arrives until the time of the node it arrived at. This is the same model
+//! [`MissileSet::collides_points`] checks moves with. Once a pawn reaches the end of its path it's
+//! assumed to stay there indefinitely.
+//!
+//! Prioritized planning is fast, but it's not complete. An agent that's planned early never makes
+//! room for the ones planned after it, so planning may fail even though a solution exists, such as
+//! when one agent parks in a corridor another one has to get through. [`plan_conflict_based`] uses
+//! Conflict-Based Search instead, which plans every agent on its own and then resolves the
+//! conflicts between their paths one at a time. It's a lot more expensive, but it lets agents make
+//! room for each other.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use ultraviolet::Vec2;
+
+use crate::math::collision_time_within;
+use crate::missile::MissileSet;
+use crate::pathfind::{Budget, FindError, Path, SearchContext, SearchProblem};
+use crate::pos::Pos;
+use crate::{Cost, FxIndexMap};
+
+/// A pawn to plan a path for.
+///
+/// The path found for an agent is the one that reaches its target the soonest, which may include
+/// waiting for other pawns to pass.
+#[derive(Copy, Clone, Debug)]
+pub struct Agent {
+    /// The key of the agent in the [`ReservationTable`].
+    pub id: u32,
+    pub start: Pos,
+    /// Reached once the agent is within `step_size` of it on both axes. The time is ignored.
+    pub target: Pos,
+    pub pawn_size: f32,
+    pub move_speed: f32,
+    /// The amount of units moved in a single step of the search.
+    pub step_size: f32,
+}
+
+/// The path a pawn has committed to.
+#[derive(Clone, Debug)]
+pub struct Reservation {
+    pub path: Vec<Pos>,
+    pub pawn_size: f32,
+    pub move_speed: f32,
+}
+
+impl Reservation {
+    /// Returns `true` if a pawn moving along `leg` runs into this one.
+    fn collides(&self, leg: &Leg, pawn_size: f32) -> bool {
+        let radius_sq = (self.pawn_size + pawn_size).powi(2);
+        path_legs(&self.path, self.move_speed).any(|reserved| reserved.collides(leg, radius_sq))
+    }
+}
+
+/// The paths of pawns that have already been planned, keyed by agent.
+#[derive(Clone, Default)]
+pub struct ReservationTable(pub FxIndexMap<u32, Reservation>);
+
+impl ReservationTable {
+    #[must_use]
+    pub fn collides_points(&self, pos_beg: &Pos, pos_end: &Pos, move_speed: f32, pawn_size: f32) -> Option<u32> {
+        self.collides_except(None, pos_beg, pos_end, move_speed, pawn_size)
+    }
+
+    /// Like [`ReservationTable::collides_points`], but ignores the reservation of `id`, which is the
+    /// one an agent that's being planned again still has from last time.
+    fn collides_except(&self, id: Option<u32>, beg: &Pos, end: &Pos, move_speed: f32, pawn_size: f32) -> Option<u32> {
+        Leg::between(beg, end, move_speed).iter().find_map(|leg| self.leg_collides_except(id, leg, pawn_size))
+    }
+
+    /// Returns the first reservation other than the one of `id` that a pawn moving along `leg` runs
+    /// into.
+    fn leg_collides_except(&self, id: Option<u32>, leg: &Leg, pawn_size: f32) -> Option<u32> {
+        let mut others = self.0.iter().filter(|&(&i, _)| Some(i) != id);
+        others.find(|(_, reserved)| reserved.collides(leg, pawn_size)).map(|(&i, _)| i)
+    }
+}
+
+/// Why [`plan_prioritized`] couldn't plan a path for every agent.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct AgentError {
+    /// The index of the agent that no path was found for.
+    pub agent: usize,
+    pub error: FindError,
+}
+
+impl std::fmt::Display for AgentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "no path for agent {}: {}", self.agent, self.error)
+    }
+}
+
+impl std::error::Error for AgentError {}
+
+/// Plans a path for each of `agents` in order, such that none of them run into `missiles`, into the
+/// pawns already in `reservations`, or into each other.
+///
+/// Every path that's found is added to `reservations` under the id of its agent, so the table can
+/// be passed on to plan more agents later. An agent that already has a path in the table, such as
+/// one from an earlier call, doesn't have to avoid it, and has it replaced by the new one. Each agent
+/// gets a search of its own, limited by `budget`. Agents that come first have priority, and are
+/// planned without regard for the ones after them.
+///
+/// Returns the paths in the same order as `agents`, or the first agent that no path was found for.
+/// If planning fails, `reservations` is left as it was before the call.
+pub fn plan_prioritized(
+    agents: &[Agent],
+    missiles: &MissileSet,
+    reservations: &mut ReservationTable,
+    budget: &Budget,
+) -> Result<Vec<Path<Pos, Cost>>, AgentError> {
+    let mut ctx = SearchContext::new();
+    // The reservations that were replaced, in order, so that they can be put back on failure.
+    let mut replaced = Vec::new();
+
+    let planned: Result<Vec<_>, _> = agents
+        .iter()
+        .enumerate()
+        .map(|(index, agent)| {
+            let constraints = &Constraints::default();
+            let mut problem = AgentProblem { agent, missiles, reservations, constraints };
+            let path =
+                ctx.search(&mut problem, agent.start, budget).map_err(|error| AgentError { agent: index, error })?;
+
+            let reservation =
+                Reservation { path: path.nodes.clone(), pawn_size: agent.pawn_size, move_speed: agent.move_speed };
+            replaced.push((agent.id, reservations.0.insert(agent.id, reservation)));
+            Ok(path)
+        })
+        .collect();
+
+    if planned.is_err() {
+        for (id, reservation) in replaced.into_iter().rev() {
+            match reservation {
+                Some(reservation) => reservations.0.insert(id, reservation),
+                None => reservations.0.shift_remove(&id),
+            };
+        }
+    }
+
+    planned
+}
+
+/// Plans a path for each of `agents` with Conflict-Based Search, such that none of them run into
+/// `missiles`, into the pawns in `reservations`, or into each other.
+///
+/// Every agent is first planned on its own. Whenever two of the paths collide, the search branches
+/// into two alternatives: one where the first agent has to avoid the motion of the second one that
+/// it collided with, and one where it's the other way around. Only the agent that got the new
+/// constraint is replanned, and the alternatives are explored from the one with the lowest total
+/// cost. An agent that collided while parked at its target also has to reach its target after the
+/// other agent's motion is over.
+///
+/// Each agent is planned with [`SearchContext::search`], limited by `search_budget`. If an agent
+/// can't be planned on its own, the error of its search is returned right away. Later on, an
+/// alternative is dropped instead if no path is found for it. `budget` limits the search over the
+/// alternatives as a whole, where every alternative that's checked for collisions counts as an
+/// expansion.
+///
+/// Returns the paths in the same order as `agents`. Since an agent is made to avoid the entire
+/// motion it collided with rather than only the part of its own path that collided, the paths are
+/// always conflict-free, but they aren't guaranteed to be the cheapest set of paths there is.
+pub fn plan_conflict_based(
+    agents: &[Agent],
+    missiles: &MissileSet,
+    reservations: &ReservationTable,
+    budget: &Budget,
+    search_budget: &Budget,
+) -> Result<Vec<Path<Pos, Cost>>, FindError> {
+    let mut ctx = SearchContext::new();
+    let mut plan = |index: usize, constraints: &Constraints| {
+        let mut problem = AgentProblem { agent: &agents[index], missiles, reservations, constraints };
+        ctx.search(&mut problem, agents[index].start, search_budget)
+    };
+
+    let constraints = vec![Constraints::default(); agents.len()];
+    let paths = (0..agents.len()).map(|index| plan(index, &constraints[index])).collect::<Result<_, _>>()?;
+
+    // The alternatives are kept in a list, and the heap refers to them by index.
+    let root = ConflictNode { constraints, paths };
+    let mut pending = BinaryHeap::from([(Reverse(root.cost()), Reverse(0))]);
+    let mut nodes = vec![Some(root)];
+    let mut expanded = 0;
+
+    while let Some((_, Reverse(index))) = pending.pop() {
+        if let Some(limit) = budget.exceeded(expanded, nodes.len()) {
+            return Err(FindError::BudgetExceeded(limit));
+        }
+
+        expanded += 1;
+
+        let node = nodes[index].take().unwrap();
+        let Some(conflict) = find_conflict(agents, &node.paths) else {
+            return Ok(node.paths);
+        };
+
+        for (own, other) in [(0, 1), (1, 0)] {
+            let agent = conflict.agents[own];
+            let (own, other, other_size) =
+                (conflict.legs[own], conflict.legs[other], agents[conflict.agents[other]].pawn_size);
+
+            let mut constraints = node.constraints.clone();
+            constraints[agent].avoid.push((other, other_size));
+
+            if own.is_parked() {
+                let after = constraints[agent].finish_after.map_or(other.end, |after| after.max(other.end));
+                constraints[agent].finish_after = Some(after);
+            }
+
+            // If the agent can't get anywhere under the new constraint, neither can this alternative.
+            let Ok(path) = plan(agent, &constraints[agent]) else {
+                continue;
+            };
+
+            let mut paths = node.paths.clone();
+            paths[agent] = path;
+
+            let child = ConflictNode { constraints, paths };
+            pending.push((Reverse(child.cost()), Reverse(nodes.len())));
+            nodes.push(Some(child));
+        }
+    }
+
+    Err(FindError::Exhausted)
+}
+
+/// One of the alternatives explored by [`plan_conflict_based`].
+struct ConflictNode {
+    // The constraints of each agent.
+    constraints: Vec<Constraints>,
+    // The path of each agent, which is the cheapest one under its constraints.
+    paths: Vec<Path<Pos, Cost>>,
+}
+
+impl ConflictNode {
+    fn cost(&self) -> Cost {
+        self.paths.iter().map(|path| path.cost).sum()
+    }
+}
+
+/// What a single agent has to do to resolve the conflicts it's been in.
+#[derive(Clone, Debug, Default)]
+struct Constraints {
+    // The motions of other agents that this agent may not run into, along with their pawn size.
+    avoid: Vec<(Leg, f32)>,
+    // The time the agent may not reach its target before.
+    finish_after: Option<f32>,
+}
+
+impl Constraints {
+    fn allows_move(&self, from: &Pos, to: &Pos, move_speed: f32, pawn_size: f32) -> bool {
+        let legs = Leg::between(from, to, move_speed);
+        self.avoid.iter().all(|(avoid, size)| legs.iter().all(|leg| !leg.collides(avoid, (size + pawn_size).powi(2))))
+    }
+}
+
+/// The earliest collision between the paths of two agents.
+struct Conflict {
+    // The agents that collided, and the parts of their paths they collided during.
+    agents: [usize; 2],
+    legs: [Leg; 2],
+}
+
+/// Finds the earliest collision between any two of `paths`.
+fn find_conflict(agents: &[Agent], paths: &[Path<Pos, Cost>]) -> Option<Conflict> {
+    let mut conflict: Option<(f32, Conflict)> = None;
+
+    for lhs in 0..agents.len() {
+        for rhs in lhs + 1..agents.len() {
+            let radius_sq = (agents[lhs].pawn_size + agents[rhs].pawn_size).powi(2);
+
+            for lhs_leg in path_legs(&paths[lhs].nodes, agents[lhs].move_speed) {
+                for rhs_leg in path_legs(&paths[rhs].nodes, agents[rhs].move_speed) {
+                    // The legs can't collide before both of them have started.
+                    let overlap = lhs_leg.beg.time().max(rhs_leg.beg.time());
+                    if conflict.as_ref().is_some_and(|&(earliest, _)| earliest <= overlap) {
+                        continue;
+                    }
+
+                    let Some(time) = lhs_leg.collision_time(&rhs_leg, radius_sq) else {
+                        continue;
+                    };
+
+                    if conflict.as_ref().is_none_or(|&(earliest, _)| time < earliest) {
+                        conflict = Some((time, Conflict { agents: [lhs, rhs], legs: [lhs_leg, rhs_leg] }));
+                    }
+                }
+            }
+        }
+    }
+
+    conflict.map(|(_, conflict)| conflict)
+}
+
+/// The legs a pawn moves along, ending with the pawn staying at the end of its path forever.
+fn path_legs(path: &[Pos], move_speed: f32) -> impl Iterator<Item = Leg> + '_ {
+    let moves = path.windows(2).flat_map(move |window| Leg::between(&window[0], &window[1], move_speed));
+    let rest = path.last().map(|&end| Leg { beg: end, velocity: Vec2::zero(), end: f32::INFINITY });
+    moves.chain(rest)
+}
+
+/// A straight piece of motion at a constant velocity, starting at `beg` and lasting until `end`.
+#[derive(Copy, Clone, Debug)]
+struct Leg {
+    beg: Pos,
+    velocity: Vec2,
+    end: f32,
+}
+
+impl Leg {
+    /// The legs of a move between two nodes: moving to `to` at `move_speed`, and then waiting there
+    /// until the time of `to`.
+    fn between(from: &Pos, to: &Pos, move_speed: f32) -> [Leg; 2] {
+        let arrival = from.time() + from.dist(to) / move_speed;
+
+        [
+            Leg { beg: *from, velocity: from.direction(to) * move_speed, end: arrival },
+            Leg { beg: Pos::from_vec(to.vec(), arrival), velocity: Vec2::zero(), end: to.time() },
+        ]
+    }
+
+    /// Whether the leg is the pawn staying at the end of its path forever.
+    fn is_parked(&self) -> bool {
+        self.end == f32::INFINITY
+    }
+
+    /// Where the leg is at `time`.
+    fn at(&self, time: f32) -> Vec2 {
+        self.beg.vec() + self.velocity * (time - self.beg.time())
+    }
+
+    fn collides(&self, other: &Leg, radius_sq: f32) -> bool {
+        self.collision_time(other, radius_sq).is_some()
+    }
+
+    /// The time the leg first runs into `other`, if it does while both of them last.
+    fn collision_time(&self, other: &Leg, radius_sq: f32) -> Option<f32> {
+        // Slice off the ends to only keep the overlapping part
+        let t_beg = self.beg.time().max(other.beg.time());
+        let t_end = self.end.min(other.end);
+
+        if t_end < t_beg {
+            return None;
+        }
+
+        let (p_lhs, p_rhs) = (self.at(t_beg), other.at(t_beg));
+        collision_time_within(p_lhs, p_rhs, self.velocity, other.velocity, radius_sq, t_end - t_beg)
+            .map(|time| t_beg + time)
+    }
+}
+
+/// The search for a single agent, which avoids both the missiles and the reserved paths.
+struct AgentProblem<'a> {
+    agent: &'a Agent,
+    missiles: &'a MissileSet,
+    reservations: &'a ReservationTable,
+    constraints: &'a Constraints,
+}
+
+impl SearchProblem for AgentProblem<'_> {
+    type Node = Pos;
+    type Cost = Cost;
+    type Successors = Vec<(Pos, Cost)>;
+
+    fn successors(&mut self, node: &Pos) -> Self::Successors {
+        let step_time = self.agent.step_size / self.agent.move_speed;
+        node.successors(step_time, self.agent.step_size)
+            .into_iter()
+            .map(|(next, _)| (next, self.movement_cost(node, &next)))
+            .collect()
+    }
+
+    fn is_valid_move(&mut self, from: &Pos, to: &Pos) -> bool {
+        let Agent { move_speed, pawn_size, .. } = *self.agent;
+        self.constraints.allows_move(from, to, move_speed, pawn_size)
+            && self.missiles.collides_points(from, to, move_speed, pawn_size).is_none()
+            && self.reservations.collides_except(Some(self.agent.id), from, to, move_speed, pawn_size).is_none()
+    }
+
+    // Waiting isn't free, or the search would wait around forever wherever it can't get past a
+    // pawn. The cost is the time taken instead, in steps, which is the distance moved for any move
+    // that doesn't wait.
+    fn movement_cost(&mut self, from: &Pos, to: &Pos) -> Cost {
+        ((to.time() - from.time()) * self.agent.move_speed / self.agent.step_size).into()
+    }
+
+    fn heuristic(&mut self, node: &Pos) -> Cost {
+        (node.dist(&self.agent.target) / self.agent.step_size).into()
+    }
+
+    // The agent stays at the end of its path forever, so it can only end there if none of the
+    // reserved pawns come by later on.
+    fn success(&mut self, node: &Pos) -> bool {
+        let parked = Leg { beg: *node, velocity: Vec2::zero(), end: f32::INFINITY };
+        node.is_same_pos(&self.agent.target, self.agent.step_size)
+            && self.constraints.finish_after.is_none_or(|after| after < node.time())
+            && self.reservations.leg_collides_except(Some(self.agent.id), &parked, self.agent.pawn_size).is_none()
+    }
+
+    fn jump_check(&mut self, from: &Pos, skip: &Pos, to: &Pos) -> Option<Pos> {
+        Pos::jump_calc(from, skip, to, self.agent.move_speed)
+    }
+}
+
+#[cfg(test)]
+fn agent(id: u32, start: (f32, f32), target: (f32, f32)) -> Agent {
+    let (start, target) = (Pos::new(start.0, start.1, 0.0), Pos::new(target.0, target.1, 0.0));
+    Agent { id, start, target, pawn_size: 20.0, move_speed: 100.0, step_size: 50.0 }
+}
+
+#[test]
+fn reservation_table_detects_head_on_collision() {
+    let mut table = ReservationTable::default();
+    let path = vec![Pos::new(0.0, 0.0, 0.0), Pos::new(500.0, 0.0, 5.0)];
+    table.0.insert(7, Reservation { path, pawn_size: 20.0, move_speed: 100.0 });
+
+    // Meets the reserved pawn halfway.
+    let (beg, end) = (Pos::new(500.0, 0.0, 0.0), Pos::new(0.0, 0.0, 5.0));
+    assert_eq!(table.collides_points(&beg, &end, 100.0, 20.0), Some(7));
+
+    // Same line, but only once the reserved pawn has passed.
+    let (beg, end) = (Pos::new(250.0, 100.0, 3.0), Pos::new(250.0, 0.0, 5.0));
+    assert_eq!(table.collides_points(&beg, &end, 100.0, 20.0), None);
+
+    // The reserved pawn stays where its path ends.
+    let (beg, end) = (Pos::new(500.0, 100.0, 10.0), Pos::new(500.0, 0.0, 11.0));
+    assert_eq!(table.collides_points(&beg, &end, 100.0, 20.0), Some(7));
+}
+
+#[test]
+fn plan_prioritized_keeps_agents_apart() {
+    let agents = [agent(0, (0.0, 0.0), (500.0, 0.0)), agent(1, (500.0, 0.0), (0.0, 0.0))];
+    let missiles = MissileSet(FxIndexMap::default());
+    let budget = Budget { max_expansions: Some(10000), ..Budget::default() };
+
+    let mut table = ReservationTable::default();
+    let paths = plan_prioritized(&agents, &missiles, &mut table, &budget).unwrap();
+
+    assert_eq!(paths.len(), 2);
+    assert_eq!(table.0.len(), 2);
+
+    for (agent, path) in agents.iter().zip(&paths) {
+        assert!(path.nodes.last().unwrap().is_same_pos(&agent.target, agent.step_size));
+    }
+
+    // Check the second agent against the first one alone, which it would've run into head on.
+    let first = ReservationTable(FxIndexMap::from_iter([(0, table.0[&0].clone())]));
+    let straight = Pos::new(0.0, 0.0, 5.0);
+    assert_eq!(first.collides_points(&agents[1].start, &straight, 100.0, 20.0), Some(0));
+    assert!(paths[1].nodes.windows(2).all(|w| first.collides_points(&w[0], &w[1], 100.0, 20.0).is_none()));
+}
+
+#[test]
+fn plan_prioritized_ignores_stale_reservation_of_same_agent() {
+    let agents = [agent(0, (0.0, 0.0), (500.0, 0.0))];
+    let missiles = MissileSet(FxIndexMap::default());
+    let budget = Budget { max_expansions: Some(10000), ..Budget::default() };
+
+    let unreserved = plan_prioritized(&agents, &missiles, &mut ReservationTable::default(), &budget).unwrap();
+
+    // Left over from an earlier plan that parked the agent halfway, right in its own way.
+    let mut table = ReservationTable::default();
+    let path = vec![Pos::new(250.0, 0.0, 0.0)];
+    table.0.insert(0, Reservation { path, pawn_size: 20.0, move_speed: 100.0 });
+
+    let paths = plan_prioritized(&agents, &missiles, &mut table, &budget).unwrap();
+    assert_eq!(paths[0].cost, unreserved[0].cost);
+    assert_eq!(table.0[&0].path, paths[0].nodes);
+}
+
+#[test]
+fn plan_prioritized_reports_blocked_agent() {
+    // The first agent parks right on the goal of the second one.
+    let agents = [agent(0, (0.0, 0.0), (300.0, 0.0)), agent(1, (600.0, 0.0), (300.0, 0.0))];
+    let missiles = MissileSet(FxIndexMap::default());
+    let budget = Budget { max_expansions: Some(1000), ..Budget::default() };
+
+    let planned = plan_prioritized(&agents, &missiles, &mut ReservationTable::default(), &budget);
+
+    let error = FindError::BudgetExceeded(crate::pathfind::Limit::Expansions);
+    assert_eq!(planned.map(|_| ()), Err(AgentError { agent: 1, error }));
+}
+
+#[test]
+fn plan_prioritized_restores_reservations_on_failure() {
+    // Same as above, with the first agent holding a reservation from an earlier plan.
+    let agents = [agent(0, (0.0, 0.0), (300.0, 0.0)), agent(1, (600.0, 0.0), (300.0, 0.0))];
+    let missiles = MissileSet(FxIndexMap::default());
+    let budget = Budget { max_expansions: Some(1000), ..Budget::default() };
+
+    let mut table = ReservationTable::default();
+    let earlier = vec![Pos::new(-500.0, 500.0, 0.0)];
+    table.0.insert(0, Reservation { path: earlier.clone(), pawn_size: 20.0, move_speed: 100.0 });
+
+    let planned = plan_prioritized(&agents, &missiles, &mut table, &budget);
+    assert_eq!(planned.map(|_| ()).map_err(|error| error.agent), Err(1));
+
+    assert_eq!(table.0.keys().collect::<Vec<_>>(), [&0]);
+    assert_eq!(table.0[&0].path, earlier);
+}
+
+#[test]
+fn plan_prioritized_waits_to_park_on_reserved_path() {
+    // The second agent's target is on the way of the first one, which only gets there 5 seconds in.
+    let agents = [agent(0, (0.0, 0.0), (1000.0, 0.0)), agent(1, (500.0, 300.0), (500.0, 0.0))];
+    let missiles = MissileSet(FxIndexMap::default());
+    let budget = Budget { max_expansions: Some(10000), ..Budget::default() };
+
+    let paths = plan_prioritized(&agents, &missiles, &mut ReservationTable::default(), &budget).unwrap();
+
+    assert!(find_conflict(&agents, &paths).is_none());
+    assert!(paths[1].nodes.last().unwrap().is_same_pos(&agents[1].target, agents[1].step_size));
+}
+
+#[cfg(test)]
+fn doorway() -> MissileSet {
+    // Static missiles making up a wall along x = 300, with a gap at y = 0 that only leaves room for
+    // a single pawn to get through.
+    let walls = (0..40).flat_map(|i| [-1.0, 1.0].map(|side| Vec2::new(300.0, side * (60.0 + i as f32 * 20.0))));
+    let wall = |origin| crate::missile::Missile {
+        time_beg: 0.0,
+        time_end: 1000.0,
+        radius: 20.0,
+        origin,
+        target: origin,
+        time_offset: Vec2::zero(),
+    };
+
+    MissileSet(walls.map(wall).enumerate().map(|(i, missile)| (i as u32, missile)).collect())
+}
+
+#[test]
+fn find_conflict_picks_earliest_collision() {
+    let agents = [0, 1, 2, 3].map(|id| agent(id, (0.0, 0.0), (0.0, 0.0)));
+    let path = |nodes: Vec<Pos>| Path {
+        costs: vec![0.0.into(); nodes.len()],
+        jumps: vec![false; nodes.len() - 1],
+        indices: (0..nodes.len()).collect(),
+        cost: 0.0.into(),
+        partial: false,
+        nodes,
+    };
+
+    // The first two are on the move from the start, but only meet halfway. The last two are parked
+    // in the same spot, which only starts to overlap a second in.
+    let paths = [
+        path(vec![Pos::new(0.0, 0.0, 0.0), Pos::new(1000.0, 0.0, 10.0)]),
+        path(vec![Pos::new(1000.0, 0.0, 0.0), Pos::new(0.0, 0.0, 10.0)]),
+        path(vec![Pos::new(0.0, 1000.0, 1.0)]),
+        path(vec![Pos::new(0.0, 1000.0, 0.0)]),
+    ];
+
+    let conflict = find_conflict(&agents, &paths).unwrap();
+    assert_eq!(conflict.agents, [2, 3]);
+
+    // The first two come within the 40 units of both their pawns once they're 960 units closer.
+    let legs = [&paths[0], &paths[1]].map(|path| path_legs(&path.nodes, 100.0).next().unwrap());
+    assert!((legs[0].collision_time(&legs[1], 40.0_f32.powi(2)).unwrap() - 4.8).abs() < 1e-3);
+}
+
+#[test]
+fn plan_conflict_based_resolves_head_on_conflict() {
+    let agents = [agent(0, (0.0, 0.0), (500.0, 0.0)), agent(1, (500.0, 0.0), (0.0, 0.0))];
+    let missiles = MissileSet(FxIndexMap::default());
+    let search_budget = Budget { max_expansions: Some(10000), ..Budget::default() };
+
+    let paths =
+        plan_conflict_based(&agents, &missiles, &ReservationTable::default(), &Budget::default(), &search_budget);
+    let paths = paths.unwrap();
+
+    assert!(find_conflict(&agents, &paths).is_none());
+    for (agent, path) in agents.iter().zip(&paths) {
+        assert!(path.nodes.last().unwrap().is_same_pos(&agent.target, agent.step_size));
+    }
+}
+
+#[test]
+fn plan_conflict_based_succeeds_where_prioritized_fails() {
+    // The first agent parks in the doorway the second one has to get through.
+    let agents = [agent(0, (0.0, 0.0), (300.0, 0.0)), agent(1, (600.0, 0.0), (-200.0, 0.0))];
+    let missiles = doorway();
+    let search_budget = Budget { max_expansions: Some(10000), ..Budget::default() };
+
+    let planned = plan_prioritized(&agents, &missiles, &mut ReservationTable::default(), &search_budget);
+    assert_eq!(planned.map(|_| ()).map_err(|error| error.agent), Err(1));
+
+    let budget = Budget { max_expansions: Some(1000), ..Budget::default() };
+    let paths = plan_conflict_based(&agents, &missiles, &ReservationTable::default(), &budget, &search_budget);
+    let paths = paths.unwrap();
+
+    assert!(find_conflict(&agents, &paths).is_none());
+    for (agent, path) in agents.iter().zip(&paths) {
+        assert!(path.nodes.last().unwrap().is_same_pos(&agent.target, agent.step_size));
+        assert!(path.nodes.windows(2).all(|w| missiles.collides_points(&w[0], &w[1], 100.0, 20.0).is_none()));
+    }
+}
+
+#[test]
+fn plan_conflict_based_reports_root_search_error() {
+    let agents = [agent(0, (0.0, 0.0), (500.0, 0.0)), agent(1, (500.0, 0.0), (0.0, 0.0))];
+    let missiles = MissileSet(FxIndexMap::default());
+    let search_budget = Budget { max_expansions: Some(1), ..Budget::default() };
+
+    let paths =
+        plan_conflict_based(&agents, &missiles, &ReservationTable::default(), &Budget::default(), &search_budget);
+
+    let error = FindError::BudgetExceeded(crate::pathfind::Limit::Expansions);
+    assert_eq!(paths.map(|_| ()), Err(error));
+}
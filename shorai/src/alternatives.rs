@@ -0,0 +1,267 @@
+//! Several meaningfully different paths to the goal from one query.
+//!
+//! Plain k-shortest paths aren't very useful here, since the second shortest path is almost always
+//! the shortest one with a tiny wiggle in it. Instead, this uses the penalty method: after a path
+//! is found, every move close to it becomes more expensive, and the search is run again. The next
+//! search then prefers to go somewhere else entirely, unless there's no reasonable alternative. What
+//! counts as close is up to the caller, which also decides how different the paths end up being.
+//!
+//! Since the penalties only ever make moves more expensive, the heuristic stays admissible and the
+//! first path is the same one a normal search would find. The costs handed out are the real costs
+//! of the paths, without any penalties.
+
+use num_traits::Zero;
+use std::ops::Mul;
+
+use crate::pathfind::{Budget, FindError, Path, SearchContext, SearchProblem};
+
+#[cfg(test)]
+use crate::pathfind::GridProblem;
+
+/// An iterator over different paths to the goal, starting with the cheapest one.
+///
+/// The iterator ends when the search is exhausted, when `budget` runs out, or when the penalties
+/// are no longer enough to push the search off the paths it already found. `budget` applies to
+/// each search separately, except for its deadline and cancellation flag.
+pub struct Alternatives<'a, P: SearchProblem, F> {
+    problem: &'a mut P,
+    ctx: SearchContext<P::Node, P::Cost>,
+    start: P::Node,
+    budget: Budget,
+    near: F,
+    penalty: f32,
+    found: Vec<Vec<P::Node>>,
+    done: bool,
+    error: Option<FindError>,
+}
+
+impl<'a, P, F> Alternatives<'a, P, F>
+where
+    P: SearchProblem,
+    F: FnMut(&P::Node, &P::Node, &[P::Node]) -> bool,
+{
+    /// `near` is called with a move and a path found earlier, and should return `true` if the move
+    /// comes close enough to the path to be penalized. Jumps can cover a lot of ground, and may
+    /// pass right over an earlier path without either end being anywhere near it, so for any-angle
+    /// problems this should check the distance between the move and the segments of the path
+    /// rather than only the nodes of either.
+    ///
+    /// Moves are made `1.0` times more expensive for every earlier path they're near.
+    #[must_use]
+    pub fn new(problem: &'a mut P, start: P::Node, budget: Budget, near: F) -> Self {
+        Alternatives {
+            problem,
+            ctx: SearchContext::new(),
+            start,
+            budget,
+            near,
+            penalty: 1.0,
+            found: Vec::new(),
+            done: false,
+            error: None,
+        }
+    }
+
+    /// Sets how much more expensive moves get for every earlier path they're near.
+    /// Higher penalties lead to more different, but also more expensive, alternatives.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `penalty` isn't positive.
+    #[must_use]
+    pub fn penalty(mut self, penalty: f32) -> Self {
+        assert!(penalty > 0.0, "penalty must be positive");
+        self.penalty = penalty;
+        self
+    }
+
+    /// Why the iteration ended, if it was because a search failed rather than because the
+    /// penalties weren't enough.
+    #[must_use]
+    pub fn error(&self) -> Option<FindError> {
+        self.error
+    }
+}
+
+impl<P, F> Iterator for Alternatives<'_, P, F>
+where
+    P: SearchProblem,
+    P::Cost: Mul<f32, Output = P::Cost>,
+    F: FnMut(&P::Node, &P::Node, &[P::Node]) -> bool,
+{
+    type Item = Path<P::Node, P::Cost>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let mut penalized =
+            Penalized { problem: &mut *self.problem, near: &mut self.near, penalty: self.penalty, found: &self.found };
+
+        let mut path = match self.ctx.search(&mut penalized, self.start, &self.budget) {
+            Ok(path) if !self.found.contains(&path.nodes) => path,
+            Ok(_) => {
+                self.done = true;
+                return None;
+            }
+            Err(error) => {
+                self.error = Some(error);
+                self.done = true;
+                return None;
+            }
+        };
+
+        // The search only saw the penalized costs, so the real ones are added up again.
+        let mut cost = P::Cost::zero();
+        for (i, w) in path.nodes.windows(2).enumerate() {
+            cost = cost + self.problem.movement_cost(&w[0], &w[1]);
+            path.costs[i + 1] = cost;
+        }
+
+        path.cost = cost;
+        self.found.push(path.nodes.clone());
+        Some(path)
+    }
+}
+
+/// Makes moves near the paths found so far more expensive.
+struct Penalized<'a, P: SearchProblem, F> {
+    problem: &'a mut P,
+    near: &'a mut F,
+    penalty: f32,
+    found: &'a [Vec<P::Node>],
+}
+
+impl<P, F> Penalized<'_, P, F>
+where
+    P: SearchProblem,
+    P::Cost: Mul<f32, Output = P::Cost>,
+    F: FnMut(&P::Node, &P::Node, &[P::Node]) -> bool,
+{
+    /// The number of paths found so far that the move from `from` to `to` is near.
+    fn near(&mut self, from: &P::Node, to: &P::Node) -> usize {
+        self.found.iter().filter(|path| (self.near)(from, to, path)).count()
+    }
+
+    fn penalize(&mut self, from: &P::Node, to: &P::Node, cost: P::Cost) -> P::Cost {
+        match self.near(from, to) {
+            0 => cost,
+            n => cost * (1.0 + self.penalty * n as f32),
+        }
+    }
+}
+
+impl<P, F> SearchProblem for Penalized<'_, P, F>
+where
+    P: SearchProblem,
+    P::Cost: Mul<f32, Output = P::Cost>,
+    F: FnMut(&P::Node, &P::Node, &[P::Node]) -> bool,
+{
+    type Node = P::Node;
+    type Cost = P::Cost;
+    type Successors = Vec<(P::Node, P::Cost)>;
+
+    fn successors(&mut self, node: &P::Node) -> Self::Successors {
+        let mut successors = self.problem.successors(node).into_iter().collect::<Vec<_>>();
+        for (to, cost) in &mut successors {
+            *cost = self.penalize(node, to, *cost);
+        }
+        successors
+    }
+
+    #[inline(always)]
+    fn is_valid_move(&mut self, from: &P::Node, to: &P::Node) -> bool {
+        self.problem.is_valid_move(from, to)
+    }
+
+    fn movement_cost(&mut self, from: &P::Node, to: &P::Node) -> P::Cost {
+        let cost = self.problem.movement_cost(from, to);
+        self.penalize(from, to, cost)
+    }
+
+    #[inline(always)]
+    fn heuristic(&mut self, node: &P::Node) -> P::Cost {
+        self.problem.heuristic(node)
+    }
+
+    #[inline(always)]
+    fn success(&mut self, node: &P::Node) -> bool {
+        self.problem.success(node)
+    }
+
+    // A jump takes the place of the plain move it skips, even if it's penalized and the move isn't,
+    // which would leave the search no way to get around an earlier path. Jumps that come near more
+    // earlier paths than that move does aren't taken for that reason.
+    fn jump_check(&mut self, from: &P::Node, skip: &P::Node, to: &P::Node) -> Option<P::Node> {
+        let jump = self.problem.jump_check(from, skip, to)?;
+        (self.near(from, &jump) <= self.near(skip, to)).then_some(jump)
+    }
+
+    #[inline(always)]
+    fn progress(&mut self, node: &P::Node) -> P::Cost {
+        self.problem.progress(node)
+    }
+
+    #[inline(always)]
+    fn tie_break(&mut self, node: &P::Node) -> P::Cost {
+        self.problem.tie_break(node)
+    }
+}
+
+#[test]
+fn alternatives_go_around_both_sides() {
+    let mut problem = GridProblem::field();
+    let optimal = SearchContext::new().search(&mut problem, (-6, 0), &Budget::default()).unwrap();
+
+    let near = |_: &(i32, i32), to: &(i32, i32), path: &[(i32, i32)]| path.contains(to);
+    let paths = Alternatives::new(&mut problem, (-6, 0), Budget::default(), near).take(2).collect::<Vec<_>>();
+
+    assert_eq!(paths.len(), 2);
+    assert_eq!(paths[0], optimal);
+
+    // One goes above the pillar and the other below it, and only the ends are shared.
+    let side = |path: &[(i32, i32)]| path.iter().map(|&(_, y)| y.signum()).sum::<i32>().signum();
+    assert_eq!(side(&paths[0].nodes) * side(&paths[1].nodes), -1);
+    assert_eq!(paths[1].nodes.iter().filter(|n| paths[0].nodes.contains(n)).count(), 2);
+    assert!(paths[1].cost >= paths[0].cost);
+
+    // The costs are the real ones, without the penalties the second search saw.
+    let real = paths[1].nodes.windows(2).map(|w| problem.movement_cost(&w[0], &w[1]).0).sum::<f32>();
+    assert!((paths[1].cost.0 - real).abs() < 1e-4);
+    assert_eq!(paths[1].costs.last(), Some(&paths[1].cost));
+}
+
+#[test]
+fn alternatives_end_when_out_of_budget() {
+    use crate::pathfind::Limit;
+
+    let mut problem = GridProblem::field();
+    let budget = Budget { max_expansions: Some(3), ..Budget::default() };
+
+    let mut alternatives = Alternatives::new(&mut problem, (-6, 0), budget, |_, to, path: &[_]| path.contains(to));
+    assert_eq!(alternatives.next(), None);
+    assert_eq!(alternatives.error(), Some(FindError::BudgetExceeded(Limit::Expansions)));
+}
+
+#[test]
+fn alternatives_penalize_jumps_over_earlier_paths() {
+    let mut problem = GridProblem::new(8, (6, 0)).with_diagonals().with_jumps();
+
+    // Whether any cell the move passes through is next to one an earlier path passes through, not
+    // counting the ends that all paths share.
+    let near = |from: &(i32, i32), to: &(i32, i32), path: &[(i32, i32)]| {
+        let cells = path.windows(2).flat_map(|w| GridProblem::cells_between(&w[0], &w[1])).collect::<Vec<_>>();
+        GridProblem::cells_between(from, to)
+            .filter(|&(x, _)| x.abs() < 6)
+            .any(|(x, y)| cells.iter().any(|&(cx, cy)| (cx - x).abs().max((cy - y).abs()) <= 1))
+    };
+
+    let paths = Alternatives::new(&mut problem, (-6, 0), Budget::default(), near).take(2).collect::<Vec<_>>();
+    assert_eq!(paths.len(), 2);
+    assert_eq!(paths[0].nodes, [(-6, 0), (6, 0)]);
+
+    // The second path jumps as well, but keeps its distance from the first one all the way.
+    assert!(paths[1].jumps.contains(&true));
+    assert!(paths[1].segments().all(|s| !near(&s.from, &s.to, &paths[0].nodes)));
+}
@@ -0,0 +1,206 @@
+//! Anytime search, which finds a path quickly and then keeps improving it while time remains.
+//!
+//! The first search runs with the heuristic inflated by a weight, which makes it greedily head for
+//! the goal and find *a* path fast, at the cost of that path being up to `weight` times as
+//! expensive as the best one (as long as the heuristic is admissible). Each following search
+//! lowers the weight, until a last search with a weight of `1.0` finds the same path a normal
+//! search would. Every path that's cheaper than the ones before it is handed out as soon as it's
+//! found, so there's always a usable path once the first search finishes.
+//!
+//! This is the restarting flavor of ARA*, where every search starts over from scratch rather than
+//! reusing the previous search tree. Restarting tends to do just as well in practice, and it keeps
+//! the lazy jump validation of the regular search intact. The buffers are still reused between
+//! searches through a [`SearchContext`].
+
+use std::ops::Mul;
+
+use crate::pathfind::{Budget, FindError, Path, SearchContext, SearchProblem};
+
+#[cfg(test)]
+use crate::pathfind::GridProblem;
+
+/// A path found by an [`Anytime`] search.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Solution<N, C> {
+    pub path: Path<N, C>,
+    /// The heuristic weight the path was found with, which bounds how far off the optimal cost it
+    /// can be if the heuristic is admissible.
+    pub weight: f32,
+}
+
+/// An iterator over increasingly cheaper paths to the goal.
+///
+/// `budget` applies to each search separately, except for its deadline and cancellation flag which
+/// are naturally shared by all of them. Once the budget runs out the iterator ends, and the last
+/// path it returned is the best one found.
+pub struct Anytime<'a, P: SearchProblem> {
+    problem: &'a mut P,
+    ctx: SearchContext<P::Node, P::Cost>,
+    start: P::Node,
+    budget: Budget,
+    // The weight for the next search, or `None` once the search with a weight of `1.0` is done.
+    weight: Option<f32>,
+    step: f32,
+    best: Option<P::Cost>,
+    error: Option<FindError>,
+}
+
+impl<'a, P: SearchProblem> Anytime<'a, P> {
+    /// Starts out with a weight of `3.0`, lowered by `0.5` after every search.
+    #[must_use]
+    pub fn new(problem: &'a mut P, start: P::Node, budget: Budget) -> Self {
+        Anytime {
+            problem,
+            ctx: SearchContext::new(),
+            start,
+            budget,
+            weight: Some(3.0),
+            step: 0.5,
+            best: None,
+            error: None,
+        }
+    }
+
+    /// Sets the weight of the first search, and how much it's lowered after every search.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `initial` is less than `1.0`, or `step` isn't positive.
+    #[must_use]
+    pub fn weights(mut self, initial: f32, step: f32) -> Self {
+        assert!(initial >= 1.0, "weight must be at least 1.0");
+        assert!(step > 0.0, "step must be positive");
+        self.weight = Some(initial);
+        self.step = step;
+        self
+    }
+
+    /// Why the iteration ended before the search with a weight of `1.0` finished, if it did.
+    #[must_use]
+    pub fn error(&self) -> Option<FindError> {
+        self.error
+    }
+}
+
+impl<P> Iterator for Anytime<'_, P>
+where
+    P: SearchProblem,
+    P::Cost: Mul<f32, Output = P::Cost>,
+{
+    type Item = Solution<P::Node, P::Cost>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(weight) = self.weight {
+            // Always finish with an unweighted search, even if the steps don't line up with it.
+            self.weight = (weight > 1.0).then(|| (weight - self.step).max(1.0));
+
+            let mut weighted = Weighted { problem: &mut *self.problem, weight };
+            match self.ctx.search(&mut weighted, self.start, &self.budget) {
+                Ok(path) => {
+                    if self.best.is_none_or(|best| path.cost < best) {
+                        self.best = Some(path.cost);
+                        return Some(Solution { path, weight });
+                    }
+                }
+                // Either the budget ran out, or there's no path at all. The weight doesn't change
+                // what's reachable, so there's no point in trying again in either case.
+                Err(error) => {
+                    self.error = Some(error);
+                    self.weight = None;
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Inflates the heuristic of another problem.
+struct Weighted<'a, P> {
+    problem: &'a mut P,
+    weight: f32,
+}
+
+impl<P> SearchProblem for Weighted<'_, P>
+where
+    P: SearchProblem,
+    P::Cost: Mul<f32, Output = P::Cost>,
+{
+    type Node = P::Node;
+    type Cost = P::Cost;
+    type Successors = P::Successors;
+
+    #[inline(always)]
+    fn successors(&mut self, node: &P::Node) -> P::Successors {
+        self.problem.successors(node)
+    }
+
+    #[inline(always)]
+    fn is_valid_move(&mut self, from: &P::Node, to: &P::Node) -> bool {
+        self.problem.is_valid_move(from, to)
+    }
+
+    #[inline(always)]
+    fn movement_cost(&mut self, from: &P::Node, to: &P::Node) -> P::Cost {
+        self.problem.movement_cost(from, to)
+    }
+
+    #[inline(always)]
+    fn heuristic(&mut self, node: &P::Node) -> P::Cost {
+        self.problem.heuristic(node) * self.weight
+    }
+
+    #[inline(always)]
+    fn success(&mut self, node: &P::Node) -> bool {
+        self.problem.success(node)
+    }
+
+    #[inline(always)]
+    fn jump_check(&mut self, from: &P::Node, skip: &P::Node, to: &P::Node) -> Option<P::Node> {
+        self.problem.jump_check(from, skip, to)
+    }
+
+    #[inline(always)]
+    fn progress(&mut self, node: &P::Node) -> P::Cost {
+        self.problem.progress(node)
+    }
+
+    #[inline(always)]
+    fn tie_break(&mut self, node: &P::Node) -> P::Cost {
+        self.problem.tie_break(node)
+    }
+}
+
+#[test]
+fn anytime_improves_until_optimal() {
+    let mut problem = GridProblem::swamp();
+    let solutions = Anytime::new(&mut problem, (-6, 0), Budget::default()).collect::<Vec<_>>();
+
+    assert!(solutions.len() > 1);
+    assert!(solutions.windows(2).all(|w| w[1].path.cost < w[0].path.cost && w[1].weight < w[0].weight));
+
+    let optimal = SearchContext::new().search(&mut problem, (-6, 0), &Budget::default()).unwrap();
+    assert_eq!(solutions.last().unwrap().path.cost, optimal.cost);
+}
+
+#[test]
+fn anytime_first_path_is_within_weight() {
+    let mut problem = GridProblem::swamp();
+    let optimal = SearchContext::new().search(&mut problem, (-6, 0), &Budget::default()).unwrap();
+
+    for solution in Anytime::new(&mut problem, (-6, 0), Budget::default()).weights(2.0, 0.25) {
+        assert!(solution.path.cost.0 <= optimal.cost.0 * solution.weight);
+    }
+}
+
+#[test]
+fn anytime_stops_when_out_of_budget() {
+    use crate::pathfind::Limit;
+
+    let mut problem = GridProblem::swamp();
+    let budget = Budget { max_expansions: Some(3), ..Budget::default() };
+
+    let mut anytime = Anytime::new(&mut problem, (-6, 0), budget);
+    assert_eq!(anytime.next(), None);
+    assert_eq!(anytime.error(), Some(FindError::BudgetExceeded(Limit::Expansions)));
+}
@@ -0,0 +1,426 @@
+//! Bidirectional search, which grows one search from the start and another from the goal until
+//! they meet in the middle.
+//!
+//! On open maps a search from one end explores a wide fan of nodes before the fan narrows towards
+//! the goal. Growing two smaller fans that meet halfway tends to need far fewer expansions, as long
+//! as the goal is a single, known node that doesn't move, and every move can be taken backwards at
+//! the same cost. That rules out time-based problems, but covers most static maps.
+//!
+//! Both halves are regular searches, including lazily validated jumps and their fallbacks. They
+//! take turns expanding a node, and each one treats every node the other half has expanded as a
+//! goal. The path is then the path to that node from the start, followed by the path from the goal
+//! to it in reverse. That makes the search stop as soon as the halves meet, which usually gives
+//! the same path a regular search would find, but unlike one it doesn't guarantee the cheapest.
+
+use std::hash::Hash;
+use std::iter;
+
+use num_traits::Zero;
+use rustc_hash::FxHashMap;
+
+use crate::pathfind::{find_inner, Budget, Event, FindError, Observer, Path, SearchContext, SearchProblem};
+
+#[cfg(test)]
+use crate::pathfind::{GridProblem, Stats};
+
+/// Searches for a path from `start` to `goal`, growing a search from both ends at once.
+///
+/// `problem` is searched forwards from `start`, and backwards from `goal`. For the backwards half,
+/// its moves are turned around, and `reverse_heuristic` takes the place of its heuristic, so it
+/// should estimate the cost of getting from `start` to a node. The `success` of `problem` isn't
+/// used, since reaching `goal` is the only way to succeed.
+///
+/// The moves of `problem` have to be reversible: every successor of a node has to have that node
+/// as a successor as well, and moving either way has to cost the same and be equally valid.
+/// `jump_check` is called with the nodes in the order the backwards half visits them, which is the
+/// reverse of the order they end up in on the path.
+///
+/// `budget` covers both halves together.
+///
+/// Since the path is put together from both halves, the `indices` of the path are the indices of
+/// the nodes in the visited map of whichever half the node came from.
+pub fn find_bidirectional<P, H>(
+    problem: &mut P,
+    start: P::Node,
+    goal: P::Node,
+    mut reverse_heuristic: H,
+    budget: &Budget,
+) -> Result<Path<P::Node, P::Cost>, FindError>
+where
+    P: SearchProblem,
+    H: FnMut(&P::Node) -> P::Cost,
+{
+    let mut forward = SearchContext::with_observer(Closed::default());
+    let mut backward = SearchContext::with_observer(Closed::default());
+    forward.seed(start);
+    backward.seed(goal);
+
+    // Every turn expands a single node from each half, and then checks on the budget.
+    let turn = Budget { max_expansions: Some(1), ..Budget::default() };
+    let mut expansions = 0;
+
+    loop {
+        let visited = forward.observer().visited + backward.observer().visited;
+        if let Some(limit) = budget.exceeded(expansions, visited) {
+            return Err(FindError::BudgetExceeded(limit));
+        }
+
+        let mut ahead = Forward { problem: &mut *problem, met: &backward.observer().expanded };
+        match find_inner(&mut forward, &mut ahead, &turn, false) {
+            // The path ends at a node the backwards half has expanded, where its path continues.
+            Ok(mut path) => {
+                let rest = backward.observer().path(path.nodes.last().unwrap()).collect::<Vec<_>>();
+
+                for pair in rest.windows(2) {
+                    let (node, _) = pair[1];
+                    path.nodes.push(node);
+                    path.jumps.push(pair[0].1);
+                    path.indices.push(backward.index_of(&node).unwrap());
+                }
+
+                recost(problem, &mut path);
+                return Ok(path);
+            }
+            Err(FindError::BudgetExceeded(_)) => {}
+            Err(error) => return Err(error),
+        }
+
+        let mut behind =
+            Backward { problem: &mut *problem, heuristic: &mut reverse_heuristic, met: &forward.observer().expanded };
+        match find_inner(&mut backward, &mut behind, &turn, false) {
+            // Same as above, except that it's the backwards path that has to be turned around.
+            Ok(back) => {
+                let rest = forward.observer().path(back.nodes.last().unwrap()).collect::<Vec<_>>();
+
+                let mut path = Path {
+                    nodes: rest.iter().rev().map(|&(node, _)| node).collect(),
+                    cost: P::Cost::zero(),
+                    partial: false,
+                    costs: Vec::new(),
+                    jumps: rest.windows(2).rev().map(|pair| pair[0].1).collect(),
+                    indices: rest.iter().rev().map(|(node, _)| forward.index_of(node).unwrap()).collect(),
+                };
+
+                path.nodes.extend(back.nodes.iter().rev().skip(1));
+                path.jumps.extend(back.jumps.iter().rev());
+                path.indices.extend(back.indices.iter().rev().skip(1));
+
+                recost(problem, &mut path);
+                return Ok(path);
+            }
+            Err(FindError::BudgetExceeded(_)) => {}
+            Err(error) => return Err(error),
+        }
+
+        expansions += 2;
+    }
+}
+
+/// Works out the costs of a path that was put together from both halves from its moves.
+///
+/// A half records the cost of a node when it's expanded, and a node may be expanded again later on
+/// through a cheaper way, which leaves the costs recorded for the nodes after it behind.
+fn recost<P: SearchProblem>(problem: &mut P, path: &mut Path<P::Node, P::Cost>) {
+    let mut costs = vec![P::Cost::zero()];
+    for pair in path.nodes.windows(2) {
+        costs.push(costs[costs.len() - 1] + problem.movement_cost(&pair[0], &pair[1]));
+    }
+
+    path.cost = costs[costs.len() - 1];
+    path.costs = costs;
+}
+
+// Every expanded node, with the parent it was expanded with, and whether it was reached by a jump.
+type Expanded<N> = FxHashMap<N, (Option<N>, bool)>;
+
+/// Keeps track of the nodes a half has expanded, along with how they were reached.
+///
+/// The visited map of a search is private to it, so this keeps a record of its own, which is what
+/// the other half looks for nodes to meet at in.
+struct Closed<N> {
+    expanded: Expanded<N>,
+    // Whether the latest popped node was reached by a jump.
+    jumped: bool,
+    // The number of nodes in the visited map.
+    visited: usize,
+}
+
+impl<N> Default for Closed<N> {
+    fn default() -> Self {
+        Closed { expanded: FxHashMap::default(), jumped: false, visited: 0 }
+    }
+}
+
+impl<N: Eq + Hash + Copy> Closed<N> {
+    /// The path from an expanded node back to the start of the half, with whether each node was
+    /// reached by a jump.
+    fn path(&self, node: &N) -> impl Iterator<Item = (N, bool)> + '_ {
+        let parent = |n: &N| self.expanded[n].0;
+        iter::successors(Some(*node), parent).map(|n| (n, self.expanded[&n].1))
+    }
+}
+
+impl<N: Eq + Hash + Copy, C> Observer<N, C> for Closed<N> {
+    fn observe(&mut self, event: Event<N, C>) {
+        match event {
+            Event::Started { .. } => *self = Closed::default(),
+            Event::Pushed { visited, .. } => self.visited = visited,
+            Event::Popped { .. } => self.jumped = false,
+            Event::JumpAccepted { .. } => self.jumped = true,
+            // Every expanded node is popped right before, and its parent was expanded before that,
+            // so following the parents always leads back to the start.
+            Event::Expanded { node, parent } => {
+                self.expanded.insert(node, (parent, self.jumped));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The half searching from the start, which is done once it reaches a node the other half expanded.
+struct Forward<'a, P: SearchProblem> {
+    problem: &'a mut P,
+    met: &'a Expanded<P::Node>,
+}
+
+impl<P: SearchProblem> SearchProblem for Forward<'_, P> {
+    type Node = P::Node;
+    type Cost = P::Cost;
+    type Successors = P::Successors;
+
+    #[inline(always)]
+    fn successors(&mut self, node: &P::Node) -> P::Successors {
+        self.problem.successors(node)
+    }
+
+    #[inline(always)]
+    fn is_valid_move(&mut self, from: &P::Node, to: &P::Node) -> bool {
+        self.problem.is_valid_move(from, to)
+    }
+
+    #[inline(always)]
+    fn movement_cost(&mut self, from: &P::Node, to: &P::Node) -> P::Cost {
+        self.problem.movement_cost(from, to)
+    }
+
+    #[inline(always)]
+    fn heuristic(&mut self, node: &P::Node) -> P::Cost {
+        self.problem.heuristic(node)
+    }
+
+    #[inline(always)]
+    fn success(&mut self, node: &P::Node) -> bool {
+        self.met.contains_key(node)
+    }
+
+    #[inline(always)]
+    fn jump_check(&mut self, from: &P::Node, skip: &P::Node, to: &P::Node) -> Option<P::Node> {
+        self.problem.jump_check(from, skip, to)
+    }
+
+    #[inline(always)]
+    fn tie_break(&mut self, node: &P::Node) -> P::Cost {
+        self.problem.tie_break(node)
+    }
+}
+
+/// The half searching from the goal, with every move turned around.
+struct Backward<'a, P: SearchProblem, H> {
+    problem: &'a mut P,
+    heuristic: &'a mut H,
+    met: &'a Expanded<P::Node>,
+}
+
+impl<P, H> SearchProblem for Backward<'_, P, H>
+where
+    P: SearchProblem,
+    H: FnMut(&P::Node) -> P::Cost,
+{
+    type Node = P::Node;
+    type Cost = P::Cost;
+    type Successors = P::Successors;
+
+    #[inline(always)]
+    fn successors(&mut self, node: &P::Node) -> P::Successors {
+        self.problem.successors(node)
+    }
+
+    #[inline(always)]
+    fn is_valid_move(&mut self, from: &P::Node, to: &P::Node) -> bool {
+        self.problem.is_valid_move(to, from)
+    }
+
+    #[inline(always)]
+    fn movement_cost(&mut self, from: &P::Node, to: &P::Node) -> P::Cost {
+        self.problem.movement_cost(to, from)
+    }
+
+    #[inline(always)]
+    fn heuristic(&mut self, node: &P::Node) -> P::Cost {
+        (self.heuristic)(node)
+    }
+
+    #[inline(always)]
+    fn success(&mut self, node: &P::Node) -> bool {
+        self.met.contains_key(node)
+    }
+
+    #[inline(always)]
+    fn jump_check(&mut self, from: &P::Node, skip: &P::Node, to: &P::Node) -> Option<P::Node> {
+        self.problem.jump_check(from, skip, to)
+    }
+
+    #[inline(always)]
+    fn tie_break(&mut self, node: &P::Node) -> P::Cost {
+        self.problem.tie_break(node)
+    }
+}
+
+#[cfg(test)]
+fn distance_to(start: (i32, i32)) -> impl FnMut(&(i32, i32)) -> crate::Cost {
+    move |&(x, y)| (((start.0 - x).pow(2) + (start.1 - y).pow(2)) as f32).sqrt().into()
+}
+
+#[test]
+fn find_bidirectional_meets_in_the_middle() {
+    // The first one meets on a node the forwards half expanded, the second on one the backwards
+    // half expanded, so that both ways of putting the path together are covered.
+    for (start, goal) in [((-8, 0), (8, 0)), ((-8, 5), (8, -5))] {
+        let mut problem = GridProblem::hall(goal);
+        let path = find_bidirectional(&mut problem, start, goal, distance_to(start), &Budget::default()).unwrap();
+
+        assert_eq!((path.nodes.first(), path.nodes.last()), (Some(&start), Some(&goal)));
+        assert_eq!(path.costs.last(), Some(&path.cost));
+
+        let mut total = crate::Cost::from(0.0);
+        for segment in path.segments() {
+            assert!(problem.is_valid_move(&segment.from, &segment.to));
+            assert!(segment.jump || problem.successors(&segment.from).into_iter().any(|(node, _)| node == segment.to));
+            assert!((segment.cost - problem.movement_cost(&segment.from, &segment.to)).0.abs() < 1e-3);
+            total += segment.cost;
+        }
+        assert!((total.0 - path.cost.0).abs() < 1e-3);
+    }
+}
+
+// A corridor from 'S' through '1' to '4' to 'Y', which leads on to 'A'. 'A' connects to the goal
+// 'G' both directly, at a high cost, and more cheaply through 'B'.
+#[cfg(test)]
+struct Detour;
+
+#[cfg(test)]
+impl Detour {
+    const EDGES: [(char, char, f32); 9] = [
+        ('S', '1', 1.0),
+        ('1', '2', 1.0),
+        ('2', '3', 1.0),
+        ('3', '4', 1.0),
+        ('4', 'Y', 1.0),
+        ('Y', 'A', 1.0),
+        ('A', 'G', 5.0),
+        ('A', 'B', 1.0),
+        ('B', 'G', 1.0),
+    ];
+}
+
+#[cfg(test)]
+impl SearchProblem for Detour {
+    type Node = char;
+    type Cost = crate::Cost;
+    type Successors = Vec<(char, crate::Cost)>;
+
+    fn successors(&mut self, &node: &char) -> Self::Successors {
+        let edges = Detour::EDGES.into_iter();
+        edges
+            .filter_map(|(a, b, cost)| match node {
+                _ if node == a => Some((b, cost.into())),
+                _ if node == b => Some((a, cost.into())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn is_valid_move(&mut self, _: &char, _: &char) -> bool {
+        true
+    }
+
+    fn movement_cost(&mut self, from: &char, to: &char) -> crate::Cost {
+        self.successors(from).into_iter().find(|(node, _)| node == to).unwrap().1
+    }
+
+    fn heuristic(&mut self, _: &char) -> crate::Cost {
+        0.0.into()
+    }
+
+    fn success(&mut self, node: &char) -> bool {
+        *node == 'G'
+    }
+
+    fn jump_check(&mut self, _: &char, _: &char, _: &char) -> Option<char> {
+        None
+    }
+}
+
+#[test]
+fn find_bidirectional_costs_follow_nodes_expanded_again() {
+    // The backwards half first expands 'A' and then 'Y' through the direct move from 'G', and only
+    // then finds the cheaper way to 'A' through 'B' and expands 'A' again. The forwards half gets to
+    // 'Y' right after, before the backwards half has expanded it again.
+    let heuristic = |node: &char| {
+        crate::Cost::from(match node {
+            'B' => 10.0,
+            '4' => 100.0,
+            _ => 0.0,
+        })
+    };
+    let path = find_bidirectional(&mut Detour, 'S', 'G', heuristic, &Budget::default()).unwrap();
+
+    assert_eq!(path.nodes, ['S', '1', '2', '3', '4', 'Y', 'A', 'B', 'G']);
+    assert_eq!(path.costs, [0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0].map(crate::Cost::from));
+    assert_eq!(path.cost, crate::Cost::from(8.0));
+}
+
+#[test]
+fn find_bidirectional_expands_less_than_a_regular_search() {
+    // A cup around the goal that opens away from the start, which a regular search floods the
+    // outside of, while the backwards half just walks out of it.
+    let blocked = GridProblem::cells(12, |x, y| (x - 8).abs().max(y.abs()) == 3 && x < 11);
+    let mut problem = GridProblem::new(12, (8, 0)).with_diagonals().with_jumps().with_blocked(blocked);
+
+    let mut context = SearchContext::with_observer(Stats::default());
+    context.search(&mut problem, (-8, 0), &Budget::default()).unwrap();
+
+    // Both halves together fit in half the expansions the regular search took.
+    let budget = Budget { max_expansions: Some(context.observer().expanded / 2), ..Budget::default() };
+    assert!(find_bidirectional(&mut problem, (-8, 0), (8, 0), distance_to((-8, 0)), &budget).is_ok());
+}
+
+#[test]
+fn find_bidirectional_handles_trivial_queries() {
+    let mut problem = GridProblem::hall((-8, 0));
+    let path = find_bidirectional(&mut problem, (-8, 0), (-8, 0), distance_to((-8, 0)), &Budget::default()).unwrap();
+    assert_eq!((path.nodes, path.cost), (vec![(-8, 0)], crate::Cost::from(0.0)));
+
+    let mut problem = GridProblem::hall((-7, 0));
+    let path = find_bidirectional(&mut problem, (-8, 0), (-7, 0), distance_to((-8, 0)), &Budget::default()).unwrap();
+    assert_eq!((path.nodes, path.costs, path.cost), (vec![(-8, 0), (-7, 0)], vec![0.0.into(), 1.0.into()], 1.0.into()));
+}
+
+#[test]
+fn find_bidirectional_exhausts_when_goal_is_enclosed() {
+    let mut problem = GridProblem::hall((10, -10));
+    let found = find_bidirectional(&mut problem, (-8, 0), (10, -10), distance_to((-8, 0)), &Budget::default());
+
+    assert_eq!(found, Err(FindError::Exhausted));
+}
+
+#[test]
+fn find_bidirectional_stops_at_expansion_limit() {
+    use crate::pathfind::Limit;
+
+    let mut problem = GridProblem::hall((8, 0));
+    let budget = Budget { max_expansions: Some(6), ..Budget::default() };
+    let found = find_bidirectional(&mut problem, (-8, 0), (8, 0), distance_to((-8, 0)), &budget);
+
+    assert_eq!(found, Err(FindError::BudgetExceeded(Limit::Expansions)));
+}
@@ -0,0 +1,320 @@
+//! Reusing paths between near-identical queries.
+//!
+//! Units tend to ask for the same path over and over again, such as every frame while they're
+//! walking along it. A [`PathCache`] remembers the paths it's given by the cell their start and
+//! goal are in, and hands them out again for later queries that start and end in the same cells.
+//!
+//! Since positions carry time, a cached path is moved along in time to start when the new query
+//! does, and its first move is adjusted to leave from the exact start of the new query. Whether the
+//! adjusted path still dodges every missile is then checked with [`MissileSet::collides_points`],
+//! and the path is only handed out if it does. That check is skipped for queries identical to the
+//! one the path was found for, as long as the missiles haven't changed since. The cache can't tell
+//! on its own whether they have, so every query comes with a version number of the missiles that
+//! has to change whenever they do.
+//!
+//! The cache assumes that moves cost in proportion to the time they take, like they do in every
+//! search over [`Pos`] in this crate, so that a path that has to be delayed costs more by as much.
+
+use crate::missile::MissileSet;
+use crate::pathfind::{FindError, Path};
+use crate::pos::Pos;
+use crate::{Cost, FxIndexMap};
+
+/// The cells the start and goal of a query are in.
+type Key = (i32, i32, i32, i32);
+
+/// Paths found by earlier queries, by the cells of their start and goal.
+pub struct PathCache {
+    // The size of the cells that queries are grouped by.
+    cell_size: f32,
+    move_speed: f32,
+    pawn_size: f32,
+    // The most paths the cache holds before it starts forgetting the oldest ones.
+    limit: Option<usize>,
+    entries: FxIndexMap<Key, Entry>,
+}
+
+struct Entry {
+    // The version of the missiles the path was last known to be valid with.
+    version: u64,
+    path: Path<Pos, Cost>,
+}
+
+impl PathCache {
+    /// Creates a cache for pawns of `pawn_size` moving at `move_speed`, which groups queries by cells
+    /// of `cell_size` units.
+    ///
+    /// Paths handed out by the cache may start and end up to a cell away from the ones the query
+    /// would've found, so the cells should be small enough for that not to matter.
+    #[must_use]
+    pub fn new(cell_size: f32, move_speed: f32, pawn_size: f32) -> PathCache {
+        PathCache { cell_size, move_speed, pawn_size, limit: None, entries: FxIndexMap::default() }
+    }
+
+    /// Keeps the cache from holding more than `limit` paths, by forgetting the oldest path whenever
+    /// a new one is added. A limit of 0 disables the cache, since it can't hold any paths at all.
+    #[must_use]
+    pub fn with_limit(mut self, limit: usize) -> PathCache {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Returns a cached path from `start` to `goal` if there's one that's still valid with `missiles`
+    /// at `version`.
+    ///
+    /// The returned path starts at `start`. If its first move takes longer from there than it had
+    /// time for, the rest of the path is delayed, and its costs go up to match. Apart from that the
+    /// path is the one that was cached, including the `indices` from the search that found it.
+    /// Cached paths that turn out to be invalid are forgotten.
+    pub fn get(&mut self, start: Pos, goal: Pos, version: u64, missiles: &MissileSet) -> Option<Path<Pos, Cost>> {
+        let key = self.key(&start, &goal);
+        let entry = self.entries.get_mut(&key)?;
+
+        if entry.version == version && entry.path.nodes[0] == start {
+            return Some(entry.path.clone());
+        }
+
+        let path = retime(&entry.path, start, self.move_speed);
+        let valid = path
+            .nodes
+            .windows(2)
+            .all(|pair| missiles.collides_points(&pair[0], &pair[1], self.move_speed, self.pawn_size).is_none());
+
+        if !valid {
+            self.entries.shift_remove(&key);
+            return None;
+        }
+
+        // Only the nodes that were checked are known to be valid, which are the cached ones only if
+        // the query starts where the cached path does.
+        if path.nodes == entry.path.nodes {
+            entry.version = version;
+        }
+
+        Some(path)
+    }
+
+    /// Caches a path found from `start` to `goal` with the missiles at `version`, replacing any
+    /// path that was cached for the same cells. Empty paths aren't cached, since they don't start
+    /// anywhere, and neither is anything with a limit of 0.
+    pub fn insert(&mut self, start: Pos, goal: Pos, version: u64, path: Path<Pos, Cost>) {
+        if path.nodes.is_empty() || self.limit == Some(0) {
+            return;
+        }
+
+        let key = self.key(&start, &goal);
+        self.entries.shift_remove(&key);
+
+        if self.limit.is_some_and(|limit| limit <= self.entries.len()) {
+            self.entries.shift_remove_index(0);
+        }
+
+        self.entries.insert(key, Entry { version, path });
+    }
+
+    /// Returns a cached path like [`PathCache::get`] does, or calls `find` to find and cache a new
+    /// one if there isn't a valid one.
+    pub fn get_or_find(
+        &mut self,
+        start: Pos,
+        goal: Pos,
+        version: u64,
+        missiles: &MissileSet,
+        find: impl FnOnce() -> Result<Path<Pos, Cost>, FindError>,
+    ) -> Result<Path<Pos, Cost>, FindError> {
+        if let Some(found) = self.get(start, goal, version, missiles) {
+            return Ok(found);
+        }
+
+        let path = find()?;
+        self.insert(start, goal, version, path.clone());
+        Ok(path)
+    }
+
+    /// Forgets every cached path.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn key(&self, start: &Pos, goal: &Pos) -> Key {
+        let cell = |value: f32| (value / self.cell_size).floor() as i32;
+        (cell(start.x()), cell(start.y()), cell(goal.x()), cell(goal.y()))
+    }
+}
+
+/// Moves `path` along in time so that it starts at `start` instead, delaying the rest of the path
+/// if the first move got longer than the time it had.
+fn retime(path: &Path<Pos, Cost>, start: Pos, move_speed: f32) -> Path<Pos, Cost> {
+    let shift = start.time() - path.nodes[0].time();
+    let mut retimed = path.clone();
+    retimed.nodes[0] = start;
+
+    if let Some(next) = path.nodes.get(1) {
+        let arrival = start.time() + start.dist(next) / move_speed;
+        let delay = (arrival - (next.time() + shift)).max(0.0);
+
+        for node in &mut retimed.nodes[1..] {
+            *node = node.next(0.0, 0.0, shift + delay);
+        }
+
+        // Only the first move takes any longer, so the whole path takes longer by the delay, and
+        // costs that much more at the rate it did.
+        let duration = path.nodes[path.nodes.len() - 1].time() - path.nodes[0].time();
+        if delay > 0.0 && duration > 0.0 {
+            let extra = Cost::from(path.cost.0 * delay / duration);
+            retimed.costs[1..].iter_mut().for_each(|cost| *cost += extra);
+            retimed.cost += extra;
+        }
+    }
+
+    retimed
+}
+
+#[cfg(test)]
+fn timed_path(nodes: Vec<Pos>, cost: f32) -> Path<Pos, Cost> {
+    // Costs in proportion to the time, which is what the cache assumes.
+    let (beg, end) = (nodes[0].time(), nodes[nodes.len() - 1].time());
+    Path {
+        costs: nodes.iter().map(|node| Cost::from(cost * (node.time() - beg) / (end - beg))).collect(),
+        jumps: vec![false; nodes.len() - 1],
+        indices: (0..nodes.len()).collect(),
+        cost: cost.into(),
+        partial: false,
+        nodes,
+    }
+}
+
+#[cfg(test)]
+fn crossing_missile() -> MissileSet {
+    use crate::missile::Missile;
+    use ultraviolet::Vec2;
+
+    let mut missiles = MissileSet(FxIndexMap::default());
+    missiles.0.insert(0, Missile::new(0.0, Vec2::new(500.0, -1000.0), Vec2::new(500.0, 1000.0), 50.0, 100.0));
+    missiles
+}
+
+#[test]
+fn cache_reuses_path_for_nearby_query() {
+    let missiles = MissileSet(FxIndexMap::default());
+    let mut cache = PathCache::new(50.0, 100.0, 20.0);
+
+    let path = timed_path(vec![Pos::new(0.0, 0.0, 0.0), Pos::new(1000.0, 0.0, 10.0)], 20.0);
+    let (start, goal) = (path.nodes[0], path.nodes[1]);
+    let found = cache.get_or_find(start, goal, 0, &missiles, || Ok(path.clone()));
+    assert_eq!(found.as_ref(), Ok(&path));
+
+    // A bit further along, a few seconds later.
+    let start = Pos::new(10.0, 0.0, 3.0);
+    let found = cache.get_or_find(start, Pos::new(1010.0, 0.0, 0.0), 0, &missiles, || unreachable!());
+    assert_eq!(found, Ok(timed_path(vec![start, Pos::new(1000.0, 0.0, 13.0)], 20.0)));
+
+    // Too far away.
+    let start = Pos::new(60.0, 0.0, 3.0);
+    assert_eq!(cache.get(start, goal, 0, &missiles), None);
+}
+
+#[test]
+fn cache_delays_path_that_starts_further_away() {
+    let missiles = MissileSet(FxIndexMap::default());
+    let mut cache = PathCache::new(50.0, 100.0, 20.0);
+
+    let path =
+        timed_path(vec![Pos::new(40.0, 0.0, 0.0), Pos::new(540.0, 0.0, 5.0), Pos::new(540.0, 500.0, 10.0)], 20.0);
+    cache.insert(path.nodes[0], path.nodes[2], 0, path.clone());
+
+    // Starting 40 units further back takes 0.4 seconds longer to get anywhere, which is 2 more per
+    // second of the path, same as the rest of it.
+    let start = Pos::new(0.0, 0.0, 0.0);
+    let found = cache.get(start, path.nodes[2], 0, &missiles).unwrap();
+    assert_eq!(found.nodes, [start, Pos::new(540.0, 0.0, 5.4), Pos::new(540.0, 500.0, 10.4)]);
+    let costs = found.costs.iter().map(|cost| cost.0).chain([found.cost.0]);
+    assert!(costs.zip([0.0, 10.8, 20.8, 20.8]).all(|(cost, expected)| (cost - expected).abs() < 1e-3));
+}
+
+#[test]
+fn cache_revalidates_path_when_missiles_change() {
+    let mut cache = PathCache::new(50.0, 100.0, 20.0);
+
+    let path = timed_path(vec![Pos::new(0.0, 0.0, 0.0), Pos::new(1000.0, 0.0, 10.0)], 20.0);
+    let (start, goal) = (path.nodes[0], path.nodes[1]);
+    cache.insert(start, goal, 0, path.clone());
+
+    // The path crosses the line of the missile long before it gets there, but not if it's started
+    // too late.
+    let missiles = crossing_missile();
+    assert_eq!(cache.get(start, goal, 1, &missiles), Some(path));
+    assert!(cache.get(start.next(0.0, 0.0, 1.0), goal, 1, &missiles).is_some());
+    assert_eq!(cache.get(start.next(0.0, 0.0, 5.0), goal, 1, &missiles), None);
+
+    // Invalid paths are forgotten, and found again.
+    assert!(cache.is_empty());
+    let found = cache.get_or_find(start, goal, 1, &missiles, || Err(FindError::Exhausted));
+    assert_eq!(found, Err(FindError::Exhausted));
+}
+
+#[test]
+fn cache_revalidates_original_start_after_shifted_query() {
+    let mut cache = PathCache::new(50.0, 100.0, 20.0);
+
+    let path = timed_path(vec![Pos::new(0.0, 0.0, 5.0), Pos::new(1000.0, 0.0, 15.0)], 20.0);
+    let (start, goal) = (path.nodes[0], path.nodes[1]);
+    cache.insert(start, goal, 0, path);
+
+    // Starting earlier dodges the missile, but that says nothing about the path as it's cached,
+    // which runs into it.
+    let missiles = crossing_missile();
+    assert!(cache.get(start.next(0.0, 0.0, -5.0), goal, 1, &missiles).is_some());
+    assert_eq!(cache.get(start, goal, 1, &missiles), None);
+}
+
+#[test]
+fn cache_ignores_empty_path() {
+    let mut cache = PathCache::new(50.0, 100.0, 20.0);
+    let (start, goal) = (Pos::new(0.0, 0.0, 0.0), Pos::new(1000.0, 0.0, 0.0));
+
+    let empty = Path { nodes: vec![], cost: 0.0.into(), partial: false, costs: vec![], jumps: vec![], indices: vec![] };
+    let found = cache.get_or_find(start, goal, 0, &MissileSet(FxIndexMap::default()), || Ok(empty.clone()));
+    assert_eq!(found, Ok(empty));
+    assert!(cache.is_empty());
+}
+
+#[test]
+fn cache_forgets_oldest_path_over_limit() {
+    let missiles = MissileSet(FxIndexMap::default());
+    let mut cache = PathCache::new(50.0, 100.0, 20.0).with_limit(2);
+
+    let paths = (0..3).map(|i| timed_path(vec![Pos::new(0.0, 0.0, 0.0), Pos::new(100.0 * i as f32, 500.0, 5.0)], 10.0));
+    for path in paths.clone() {
+        cache.insert(path.nodes[0], path.nodes[1], 0, path);
+    }
+
+    assert_eq!(cache.len(), 2);
+
+    let cached = paths.map(|path| cache.get(path.nodes[0], path.nodes[1], 0, &missiles).is_some()).collect::<Vec<_>>();
+    assert_eq!(cached, [false, true, true]);
+}
+
+#[test]
+fn cache_with_zero_limit_holds_nothing() {
+    let missiles = MissileSet(FxIndexMap::default());
+    let mut cache = PathCache::new(50.0, 100.0, 20.0).with_limit(0);
+
+    let path = timed_path(vec![Pos::new(0.0, 0.0, 0.0), Pos::new(1000.0, 0.0, 10.0)], 20.0);
+    let (start, goal) = (path.nodes[0], path.nodes[1]);
+    let found = cache.get_or_find(start, goal, 0, &missiles, || Ok(path.clone()));
+    assert_eq!(found.as_ref(), Ok(&path));
+
+    assert!(cache.is_empty());
+    assert_eq!(cache.get(start, goal, 0, &missiles), None);
+}
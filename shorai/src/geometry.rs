@@ -24,6 +24,9 @@ impl Line {
         let t = (p - v).dot(d) / l2;
 
         // We clamp t from [0,1] to handle points outside the segment vw.
+        // This intentionally isn't `clamp`, since `t` is NaN for zero-length lines and `max` maps
+        // that to 0.0 for us.
+        #[allow(clippy::manual_clamp)]
         let t = t.max(0.0).min(1.0);
 
         // Projection falls on the segment
@@ -0,0 +1,225 @@
+//! Searching for whichever of several goals is the best one to go to.
+//!
+//! Units often don't need to get to one particular node, but to any one of a handful, such as the
+//! cover positions around them. Some of those may be better than others, so each goal comes with a
+//! bonus that's taken off the cost of the paths ending there. The search then finds the goal with
+//! the lowest cost after the bonus, rather than simply the closest one.
+//!
+//! Bonuses can't be taken off the cost directly without making it negative, which the search
+//! doesn't allow. Instead every goal gets an extra move to a shared finish, which costs the
+//! difference between the largest bonus and the goal's own bonus. The heuristic is the lowest
+//! estimate over all goals of getting there and on to the finish, which stays admissible as long as
+//! the estimate for every single goal is.
+
+use std::ops::Sub;
+use std::{iter, option};
+
+use num_traits::Zero;
+use rustc_hash::FxHashMap;
+
+use crate::pathfind::{Budget, FindError, Path, SearchContext, SearchProblem};
+
+#[cfg(test)]
+use crate::pathfind::{grid_dist, GridProblem};
+
+/// A path found by [`find_best_goal`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GoalPath<N, C> {
+    /// The path to the goal. Its costs don't include the bonus of the goal.
+    pub path: Path<N, C>,
+    /// The index into the goals of the goal the path ends at.
+    pub goal: usize,
+}
+
+/// Searches for the goal in `goals` with the lowest cost of getting there after taking off its
+/// bonus, and returns the path to it.
+///
+/// Each goal is a node along with its bonus. `distance` takes the place of the heuristic of
+/// `problem`, and should estimate the cost of getting from a node to a goal without overestimating
+/// it. The `success` of `problem` isn't used either, since reaching one of the goals is the only way
+/// to succeed. If the same node is listed more than once, only its largest bonus counts.
+///
+/// Returns [`FindError::InvalidInput`] if `goals` is empty.
+pub fn find_best_goal<P, D>(
+    problem: &mut P,
+    start: P::Node,
+    goals: &[(P::Node, P::Cost)],
+    mut distance: D,
+    budget: &Budget,
+) -> Result<GoalPath<P::Node, P::Cost>, FindError>
+where
+    P: SearchProblem,
+    P::Cost: Sub<Output = P::Cost>,
+    D: FnMut(&P::Node, &P::Node) -> P::Cost,
+{
+    let Some(best) = goals.iter().map(|&(_, bonus)| bonus).max() else {
+        return Err(FindError::InvalidInput);
+    };
+
+    let mut finishes = FxHashMap::default();
+    for (index, &(node, bonus)) in goals.iter().enumerate() {
+        let finish = finishes.entry(node).or_insert(index);
+        if goals[*finish].1 < bonus {
+            *finish = index;
+        }
+    }
+
+    let mut multi = MultiGoal { problem, goals, best, finishes, distance: &mut distance };
+    let mut path = SearchContext::new().search(&mut multi, Multi::At(start), budget)?;
+
+    // The last node is always the finish, reached from the goal right before it.
+    let Some(Multi::Finish(goal)) = path.nodes.pop() else { unreachable!() };
+    path.costs.pop();
+    path.jumps.pop();
+    path.indices.pop();
+
+    let nodes = path.nodes.into_iter().map(|node| match node {
+        Multi::At(node) => node,
+        Multi::Finish(_) => unreachable!(),
+    });
+
+    let Path { costs, jumps, indices, partial, .. } = path;
+    let path = Path { nodes: nodes.collect(), cost: *costs.last().unwrap(), partial, costs, jumps, indices };
+    Ok(GoalPath { path, goal })
+}
+
+/// A node of the problem being searched, or the finish reached through one of the goals.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+enum Multi<N> {
+    At(N),
+    Finish(usize),
+}
+
+struct MultiGoal<'a, P: SearchProblem, D> {
+    problem: &'a mut P,
+    goals: &'a [(P::Node, P::Cost)],
+    // The largest bonus of any goal.
+    best: P::Cost,
+    // The goal each goal node leads to the finish through, which is the one with the largest bonus.
+    finishes: FxHashMap<P::Node, usize>,
+    distance: &'a mut D,
+}
+
+type MultiMove<P> = (Multi<<P as SearchProblem>::Node>, <P as SearchProblem>::Cost);
+type MultiSuccessors<P> = iter::Chain<
+    iter::Map<
+        <<P as SearchProblem>::Successors as IntoIterator>::IntoIter,
+        fn((<P as SearchProblem>::Node, <P as SearchProblem>::Cost)) -> MultiMove<P>,
+    >,
+    option::IntoIter<MultiMove<P>>,
+>;
+
+impl<P, D> MultiGoal<'_, P, D>
+where
+    P: SearchProblem,
+    P::Cost: Sub<Output = P::Cost>,
+{
+    /// The cost of the move from goal `index` to the finish.
+    fn finish_cost(&self, index: usize) -> P::Cost {
+        self.best - self.goals[index].1
+    }
+}
+
+impl<P, D> SearchProblem for MultiGoal<'_, P, D>
+where
+    P: SearchProblem,
+    P::Cost: Sub<Output = P::Cost>,
+    D: FnMut(&P::Node, &P::Node) -> P::Cost,
+{
+    type Node = Multi<P::Node>;
+    type Cost = P::Cost;
+    type Successors = MultiSuccessors<P>;
+
+    fn successors(&mut self, node: &Self::Node) -> Self::Successors {
+        let Multi::At(node) = node else { unreachable!("the finish is never expanded") };
+
+        let finish = self.finishes.get(node).map(|&index| (Multi::Finish(index), self.finish_cost(index)));
+        let at: fn(_) -> _ = |(node, cost)| (Multi::At(node), cost);
+        self.problem.successors(node).into_iter().map(at).chain(finish)
+    }
+
+    #[inline(always)]
+    fn is_valid_move(&mut self, from: &Self::Node, to: &Self::Node) -> bool {
+        match (from, to) {
+            (Multi::At(from), Multi::At(to)) => self.problem.is_valid_move(from, to),
+            _ => true,
+        }
+    }
+
+    #[inline(always)]
+    fn movement_cost(&mut self, from: &Self::Node, to: &Self::Node) -> Self::Cost {
+        match (from, to) {
+            (Multi::At(from), Multi::At(to)) => self.problem.movement_cost(from, to),
+            (_, &Multi::Finish(index)) => self.finish_cost(index),
+            (Multi::Finish(_), Multi::At(_)) => unreachable!("the finish is never expanded"),
+        }
+    }
+
+    fn heuristic(&mut self, node: &Self::Node) -> Self::Cost {
+        let Multi::At(node) = node else {
+            return Zero::zero();
+        };
+
+        // There's always at least one goal, so there's always a lowest estimate.
+        let estimates = self.goals.iter().map(|&(goal, bonus)| (self.distance)(node, &goal) + self.best - bonus);
+        estimates.min().unwrap()
+    }
+
+    #[inline(always)]
+    fn success(&mut self, node: &Self::Node) -> bool {
+        matches!(node, Multi::Finish(_))
+    }
+
+    #[inline(always)]
+    fn jump_check(&mut self, from: &Self::Node, skip: &Self::Node, to: &Self::Node) -> Option<Self::Node> {
+        // Jumping to the finish would skip the goal it's reached through.
+        match (from, skip, to) {
+            (Multi::At(from), Multi::At(skip), Multi::At(to)) => self.problem.jump_check(from, skip, to).map(Multi::At),
+            _ => None,
+        }
+    }
+
+    #[inline(always)]
+    fn tie_break(&mut self, node: &Self::Node) -> Self::Cost {
+        match node {
+            Multi::At(node) => self.problem.tie_break(node),
+            Multi::Finish(_) => Zero::zero(),
+        }
+    }
+}
+
+#[test]
+fn find_best_goal_goes_to_cheapest_goal() {
+    // (4, 0) is closer as the crow flies, but the wall makes (-5, 0) cheaper to get to.
+    let goals = [((4, 0), 0.0.into()), ((-5, 0), 0.0.into())];
+    let found = find_best_goal(&mut GridProblem::yard(), (0, 0), &goals, grid_dist, &Budget::default()).unwrap();
+
+    assert_eq!(found.goal, 1);
+    assert_eq!((found.path.nodes.first(), found.path.nodes.last()), (Some(&(0, 0)), Some(&(-5, 0))));
+    assert_eq!(found.path.cost.0, 5.0);
+
+    // The finish the search went on to after the goal is left out everywhere.
+    assert_eq!((found.path.costs.len(), found.path.costs.last()), (found.path.nodes.len(), Some(&found.path.cost)));
+    assert_eq!(found.path.jumps.len(), found.path.nodes.len() - 1);
+    assert_eq!(found.path.indices.len(), found.path.nodes.len());
+}
+
+#[test]
+fn find_best_goal_takes_off_bonus() {
+    let goals = [((-5, 0), 0.0.into()), ((0, 8), 4.0.into()), ((0, 8), 1.0.into())];
+    let found = find_best_goal(&mut GridProblem::yard(), (0, 0), &goals, grid_dist, &Budget::default()).unwrap();
+
+    assert_eq!(found.goal, 1);
+    assert_eq!(found.path.nodes.last(), Some(&(0, 8)));
+    assert_eq!(found.path.cost.0, 8.0);
+}
+
+#[test]
+fn find_best_goal_reports_errors() {
+    let outside = [((20, 0), 0.0.into())];
+    let found = find_best_goal(&mut GridProblem::yard(), (0, 0), &outside, grid_dist, &Budget::default());
+    assert_eq!(found, Err(FindError::Exhausted));
+
+    let found = find_best_goal(&mut GridProblem::yard(), (0, 0), &[], grid_dist, &Budget::default());
+    assert_eq!(found, Err(FindError::InvalidInput));
+}
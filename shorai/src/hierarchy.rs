@@ -0,0 +1,672 @@
+//! Hierarchical pathfinding for maps that are too large to search in one go.
+//!
+//! On a large map, most of the nodes a search expands are spent on finding a way around walls that
+//! are far away from where the interesting part of the path is. A [`Hierarchy`] splits the map up
+//! into square clusters in the spirit of HPA*, and keeps an abstract graph of how to get from one
+//! cluster to the next:
+//!
+//! - Wherever the border between two neighboring clusters can be crossed, an entrance is placed in
+//!   the middle of every open stretch of the border.
+//! - Within a cluster, the entrances are connected by the cost of the cheapest way between them
+//!   that stays inside the cluster.
+//!
+//! A query first searches the abstract graph for a coarse route to the goal, which only touches a
+//! handful of nodes per cluster. The route is then refined leg by leg with the regular time-aware
+//! search, each leg heading for the next entrance along the route. Only the last leg searches for
+//! the actual goal of the problem.
+//!
+//! The abstract graph only knows about obstacles that stay put, such as walls, which are described
+//! by an `is_clear` callback between two points. Moving obstacles like missiles are left to the
+//! refinement. Whenever the static obstacles change, the clusters they're in need to be invalidated
+//! with [`Hierarchy::invalidate`], after which they're rebuilt by the next query.
+
+use std::mem;
+
+use num_traits::Zero;
+use rustc_hash::{FxHashMap, FxHashSet};
+use ultraviolet::Vec2;
+
+use crate::pathfind::{Budget, FindError, Path, SearchContext, SearchProblem};
+use crate::pos::Pos;
+use crate::Cost;
+
+/// A cell of the grid the abstract graph is built on.
+type Cell = (i32, i32);
+
+/// The index of a cluster along each axis.
+type ClusterId = (i32, i32);
+
+/// How cells and clusters are laid out on the map.
+#[derive(Copy, Clone, Debug)]
+struct Grid {
+    // The position of cell (0, 0), which is the top left corner of the map.
+    origin: Vec2,
+    cell_size: f32,
+    // The amount of cells along each side of a cluster.
+    cluster_cells: i32,
+}
+
+impl Grid {
+    fn cluster_of(&self, (x, y): Cell) -> ClusterId {
+        (x.div_euclid(self.cluster_cells), y.div_euclid(self.cluster_cells))
+    }
+
+    fn cell_of(&self, point: Vec2) -> Cell {
+        let cell = (point - self.origin) / self.cell_size;
+        (cell.x.round() as i32, cell.y.round() as i32)
+    }
+
+    fn point_of(&self, (x, y): Cell) -> Vec2 {
+        self.origin + Vec2::new(x as f32, y as f32) * self.cell_size
+    }
+}
+
+/// An abstract graph of how to get between the clusters of a map.
+pub struct Hierarchy {
+    grid: Grid,
+    // The amount of clusters along each axis.
+    clusters: (i32, i32),
+    // Clusters that have been invalidated since the graph was last built.
+    dirty: FxHashSet<ClusterId>,
+    // The crossings of each border, keyed by the clusters on either side of it, with the cluster
+    // to the left or above first. Each crossing is the cell on either side of the border.
+    borders: FxHashMap<(ClusterId, ClusterId), Vec<(Cell, Cell)>>,
+    // The ways between the entrances of each cluster, along with their cost.
+    edges: FxHashMap<ClusterId, Vec<(Cell, Cell, Cost)>>,
+    // Every edge of the abstract graph, by the cell it starts at.
+    graph: FxHashMap<Cell, Vec<(Cell, Cost)>>,
+    // Reused by the searches within clusters and on the abstract graph.
+    ctx: SearchContext<Cell, Cost>,
+}
+
+impl Hierarchy {
+    /// Creates an abstract graph for a map of `size` units starting at `origin`, made up of
+    /// clusters of `cluster_cells` by `cluster_cells` cells that are `cell_size` units wide.
+    ///
+    /// The graph is built by the first query.
+    #[must_use]
+    pub fn new(origin: Vec2, size: Vec2, cell_size: f32, cluster_cells: usize) -> Hierarchy {
+        let cluster_cells = cluster_cells as i32;
+        let cluster_size = cell_size * cluster_cells as f32;
+        let clusters = ((size.x / cluster_size).ceil() as i32, (size.y / cluster_size).ceil() as i32);
+
+        Hierarchy {
+            grid: Grid { origin, cell_size, cluster_cells },
+            clusters,
+            dirty: (0..clusters.0).flat_map(|x| (0..clusters.1).map(move |y| (x, y))).collect(),
+            borders: FxHashMap::default(),
+            edges: FxHashMap::default(),
+            graph: FxHashMap::default(),
+            ctx: SearchContext::new(),
+        }
+    }
+
+    /// Marks every cluster overlapping the area between `min` and `max` as changed, so that it's
+    /// rebuilt before the next query.
+    pub fn invalidate(&mut self, min: Vec2, max: Vec2) {
+        let (min, max) = (self.grid.cluster_of(self.grid.cell_of(min)), self.grid.cluster_of(self.grid.cell_of(max)));
+
+        for x in min.0.max(0)..=max.0.min(self.clusters.0 - 1) {
+            for y in min.1.max(0)..=max.1.min(self.clusters.1 - 1) {
+                self.dirty.insert((x, y));
+            }
+        }
+    }
+
+    /// Searches the abstract graph for a coarse route from `start` to `goal`.
+    ///
+    /// `is_clear` returns `true` if a pawn can move straight from one point to another without
+    /// running into any static obstacles, and is expected to give the same answer both ways. It's
+    /// only called for the clusters that have to be rebuilt, and to connect the start and goal to
+    /// the graph.
+    ///
+    /// The route is made up of the cells the start and goal are in, and the entrances between
+    /// them. Returns `None` if either is outside of the map, or if there's no way between them.
+    pub fn plan(&mut self, mut is_clear: impl FnMut(Vec2, Vec2) -> bool, start: Vec2, goal: Vec2) -> Option<Vec<Vec2>> {
+        self.rebuild(&mut is_clear);
+
+        let (start, goal) = (self.grid.cell_of(start), self.grid.cell_of(goal));
+        if !self.contains(start) || !self.contains(goal) {
+            return None;
+        }
+
+        // Connect the start and goal to the entrances of their clusters, and to each other if
+        // they're in the same cluster, without adding them to the graph for good.
+        let mut extra: FxHashMap<Cell, Vec<(Cell, Cost)>> = FxHashMap::default();
+        let (start_cluster, goal_cluster) = (self.grid.cluster_of(start), self.grid.cluster_of(goal));
+
+        for entrance in self.entrances(start_cluster) {
+            if let Some(cost) = self.cluster_cost(&mut is_clear, start_cluster, start, entrance) {
+                extra.entry(start).or_default().push((entrance, cost));
+            }
+        }
+
+        for entrance in self.entrances(goal_cluster) {
+            if let Some(cost) = self.cluster_cost(&mut is_clear, goal_cluster, entrance, goal) {
+                extra.entry(entrance).or_default().push((goal, cost));
+            }
+        }
+
+        if start_cluster == goal_cluster {
+            if let Some(cost) = self.cluster_cost(&mut is_clear, start_cluster, start, goal) {
+                extra.entry(start).or_default().push((goal, cost));
+            }
+        }
+
+        let Hierarchy { grid, graph, ctx, .. } = self;
+        let successors = |cell: &Cell| {
+            let edges = graph.get(cell).into_iter().chain(extra.get(cell));
+            edges.flatten().copied().collect::<Vec<_>>()
+        };
+
+        let point = |&cell: &Cell| grid.point_of(cell);
+        let distance = |from: &Cell, to: &Cell| Cost::from((point(to) - point(from)).mag());
+        let cells = ctx
+            .find(
+                start,
+                successors,
+                |_, _| true,
+                distance,
+                |cell| distance(cell, &goal),
+                |&cell| cell == goal,
+                |_, _, _| None,
+            )
+            .ok()?;
+
+        Some(cells.nodes.iter().map(point).collect())
+    }
+
+    /// Finds a path from `start` to a goal of `problem` by planning a coarse route to `goal` and
+    /// then refining it.
+    ///
+    /// `is_clear` describes the static obstacles of the map, see [`Hierarchy::plan`]. Every leg of
+    /// the route but the last one is searched with the heuristic of `problem` replaced by
+    /// `distance`, and succeeds once it's within a cell of the entrance it heads for. The last leg
+    /// is searched with `problem` as is. Each leg gets a search of its own, limited by `budget`.
+    ///
+    /// The entrance a leg heads for is passed to `distance` at the soonest time a pawn moving at
+    /// `move_speed` could get there, going straight from where the leg starts.
+    ///
+    /// The returned path is the legs put together, so its `indices` are the ones each node had in
+    /// the search of its own leg. Returns [`FindError::Exhausted`] if there's no coarse route to
+    /// `goal`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn find<P, D>(
+        &mut self,
+        is_clear: impl FnMut(Vec2, Vec2) -> bool,
+        problem: &mut P,
+        mut distance: D,
+        start: Pos,
+        goal: Vec2,
+        move_speed: f32,
+        budget: &Budget,
+    ) -> Result<Path<Pos, P::Cost>, FindError>
+    where
+        P: SearchProblem<Node = Pos>,
+        D: FnMut(&Pos, &Pos) -> P::Cost,
+    {
+        let route = self.plan(is_clear, start.vec(), goal).ok_or(FindError::Exhausted)?;
+
+        // A route crosses every border as a pair of neighboring cells, so only the cell on the
+        // far side of each border is worth heading for. The start and goal aren't waypoints.
+        let near = |from: Vec2, to: Vec2| (to - from).abs().component_max() <= self.grid.cell_size * 1.5;
+        let waypoints = route.windows(2).skip(1).filter(|pair| !near(pair[0], pair[1])).map(|pair| pair[0]);
+
+        let mut ctx = SearchContext::new();
+        let zero = P::Cost::zero();
+        let mut path =
+            Path { nodes: vec![start], cost: zero, partial: false, costs: vec![zero], jumps: vec![], indices: vec![0] };
+
+        for waypoint in waypoints {
+            let from = path.nodes[path.nodes.len() - 1];
+            let arrival = from.time() + (waypoint - from.vec()).mag() / move_speed;
+            let mut toward = Toward {
+                problem: &mut *problem,
+                waypoint: Pos::from_vec(waypoint, arrival),
+                reach: self.grid.cell_size,
+                distance: &mut distance,
+            };
+
+            append(&mut path, ctx.search(&mut toward, from, budget)?);
+        }
+
+        let from = path.nodes[path.nodes.len() - 1];
+        append(&mut path, ctx.search(problem, from, budget)?);
+
+        Ok(path)
+    }
+
+    /// Rebuilds the parts of the graph that were invalidated.
+    fn rebuild(&mut self, is_clear: &mut impl FnMut(Vec2, Vec2) -> bool) {
+        if self.dirty.is_empty() {
+            return;
+        }
+
+        // The entrances of a cluster change along with the borders of its neighbors, so those
+        // have their edges rebuilt as well. Everything else in the graph stays as it is.
+        let dirty = mem::take(&mut self.dirty);
+        let mut borders = FxHashSet::default();
+        let mut touched = FxHashSet::default();
+
+        for &cluster in &dirty {
+            touched.insert(cluster);
+
+            for neighbor in self.neighbors(cluster) {
+                touched.insert(neighbor);
+                borders.insert(if cluster < neighbor { (cluster, neighbor) } else { (neighbor, cluster) });
+            }
+        }
+
+        let crossing_cost = Cost::from(self.grid.cell_size);
+        for border in borders {
+            let crossings = self.crossings(is_clear, border);
+
+            for (from, to) in self.borders.insert(border, crossings.clone()).into_iter().flatten() {
+                self.unlink(from, to);
+            }
+            for (from, to) in crossings {
+                self.link(from, to, crossing_cost);
+            }
+        }
+
+        for cluster in touched {
+            let entrances = self.entrances(cluster);
+
+            let mut edges = Vec::new();
+            for (i, &from) in entrances.iter().enumerate() {
+                for &to in &entrances[i + 1..] {
+                    if let Some(cost) = self.cluster_cost(is_clear, cluster, from, to) {
+                        edges.push((from, to, cost));
+                    }
+                }
+            }
+
+            for (from, to, _) in self.edges.insert(cluster, edges.clone()).into_iter().flatten() {
+                self.unlink(from, to);
+            }
+            for (from, to, cost) in edges {
+                self.link(from, to, cost);
+            }
+        }
+    }
+
+    /// Adds an edge between two cells to the graph. Moves are assumed to be just as possible
+    /// backwards as they are forwards, so it goes both ways.
+    fn link(&mut self, from: Cell, to: Cell, cost: Cost) {
+        self.graph.entry(from).or_default().push((to, cost));
+        self.graph.entry(to).or_default().push((from, cost));
+    }
+
+    /// Removes the edge between two cells from the graph, along with cells that are left without
+    /// any edges.
+    fn unlink(&mut self, from: Cell, to: Cell) {
+        for (from, to) in [(from, to), (to, from)] {
+            if let Some(edges) = self.graph.get_mut(&from) {
+                edges.retain(|&(cell, _)| cell != to);
+                if edges.is_empty() {
+                    self.graph.remove(&from);
+                }
+            }
+        }
+    }
+
+    /// Finds the crossings of the border between two neighboring clusters, one in the middle of
+    /// every open stretch of the border.
+    fn crossings(
+        &self,
+        is_clear: &mut impl FnMut(Vec2, Vec2) -> bool,
+        (lhs, rhs): (ClusterId, ClusterId),
+    ) -> Vec<(Cell, Cell)> {
+        let k = self.grid.cluster_cells;
+
+        // The pair of cells on either side of the border at each offset along it.
+        let pair = |i: i32| match (rhs.0 - lhs.0, rhs.1 - lhs.1) {
+            (1, 0) => ((rhs.0 * k - 1, lhs.1 * k + i), (rhs.0 * k, lhs.1 * k + i)),
+            _ => ((lhs.0 * k + i, rhs.1 * k - 1), (lhs.0 * k + i, rhs.1 * k)),
+        };
+
+        let mut crossings = Vec::new();
+        let mut open = None;
+
+        for i in 0..=k {
+            let clear = i < k && {
+                let (from, to) = pair(i);
+                is_clear(self.grid.point_of(from), self.grid.point_of(to))
+            };
+
+            match (clear, open) {
+                (true, None) => open = Some(i),
+                (false, Some(beg)) => {
+                    crossings.push(pair((beg + i - 1) / 2));
+                    open = None;
+                }
+                _ => {}
+            }
+        }
+
+        crossings
+    }
+
+    /// The cost of the cheapest way between two cells of a cluster that stays inside of it.
+    fn cluster_cost(
+        &mut self,
+        is_clear: &mut impl FnMut(Vec2, Vec2) -> bool,
+        cluster: ClusterId,
+        from: Cell,
+        to: Cell,
+    ) -> Option<Cost> {
+        let grid = self.grid;
+        let inside = |&cell: &Cell| grid.cluster_of(cell) == cluster;
+        let point = |&cell: &Cell| grid.point_of(cell);
+        let cell_size = grid.cell_size;
+
+        let successors = |&(x, y): &Cell| {
+            const DIA: f32 = std::f32::consts::SQRT_2;
+
+            [
+                (1, 0, 1.0),
+                (0, 1, 1.0),
+                (-1, 0, 1.0),
+                (0, -1, 1.0),
+                (1, 1, DIA),
+                (-1, 1, DIA),
+                (-1, -1, DIA),
+                (1, -1, DIA),
+            ]
+            .map(|(dx, dy, cost)| ((x + dx, y + dy), Cost::from(cost * cell_size)))
+        };
+
+        let distance = |from: &Cell, to: &Cell| Cost::from((point(to) - point(from)).mag());
+        let is_valid_move = |from: &Cell, to: &Cell| inside(to) && is_clear(point(from), point(to));
+
+        let found = self.ctx.find(
+            from,
+            successors,
+            is_valid_move,
+            distance,
+            |cell| distance(cell, &to),
+            |&cell| cell == to,
+            |_, _, _| None,
+        );
+
+        found.ok().map(|path| path.cost)
+    }
+
+    /// The cells on the near side of every crossing of the borders of `cluster`.
+    fn entrances(&self, cluster: ClusterId) -> Vec<Cell> {
+        let mut entrances = Vec::new();
+
+        for neighbor in self.neighbors(cluster) {
+            let (border, near) = if cluster < neighbor { ((cluster, neighbor), 0) } else { ((neighbor, cluster), 1) };
+            let crossings = self.borders.get(&border).into_iter().flatten();
+            entrances.extend(crossings.map(|&crossing| [crossing.0, crossing.1][near]));
+        }
+
+        entrances
+    }
+
+    fn neighbors(&self, (x, y): ClusterId) -> impl Iterator<Item = ClusterId> + '_ {
+        let (w, h) = self.clusters;
+        [(x + 1, y), (x, y + 1), (x - 1, y), (x, y - 1)]
+            .into_iter()
+            .filter(move |&(x, y)| 0 <= x && x < w && 0 <= y && y < h)
+    }
+
+    fn contains(&self, cell: Cell) -> bool {
+        let (x, y) = self.grid.cluster_of(cell);
+        0 <= x && x < self.clusters.0 && 0 <= y && y < self.clusters.1
+    }
+}
+
+/// A leg of a route, heading for one of the entrances along the way.
+struct Toward<'a, P, D> {
+    problem: &'a mut P,
+    waypoint: Pos,
+    // How close to the waypoint counts as having reached it.
+    reach: f32,
+    distance: &'a mut D,
+}
+
+impl<P, D> SearchProblem for Toward<'_, P, D>
+where
+    P: SearchProblem<Node = Pos>,
+    D: FnMut(&Pos, &Pos) -> P::Cost,
+{
+    type Node = Pos;
+    type Cost = P::Cost;
+    type Successors = P::Successors;
+
+    #[inline(always)]
+    fn successors(&mut self, node: &Pos) -> P::Successors {
+        self.problem.successors(node)
+    }
+
+    #[inline(always)]
+    fn is_valid_move(&mut self, from: &Pos, to: &Pos) -> bool {
+        self.problem.is_valid_move(from, to)
+    }
+
+    #[inline(always)]
+    fn movement_cost(&mut self, from: &Pos, to: &Pos) -> P::Cost {
+        self.problem.movement_cost(from, to)
+    }
+
+    #[inline(always)]
+    fn heuristic(&mut self, node: &Pos) -> P::Cost {
+        (self.distance)(node, &self.waypoint)
+    }
+
+    #[inline(always)]
+    fn success(&mut self, node: &Pos) -> bool {
+        node.is_same_pos(&self.waypoint, self.reach)
+    }
+
+    #[inline(always)]
+    fn jump_check(&mut self, from: &Pos, skip: &Pos, to: &Pos) -> Option<Pos> {
+        self.problem.jump_check(from, skip, to)
+    }
+
+    #[inline(always)]
+    fn progress(&mut self, node: &Pos) -> P::Cost {
+        (self.distance)(node, &self.waypoint)
+    }
+
+    #[inline(always)]
+    fn tie_break(&mut self, node: &Pos) -> P::Cost {
+        self.problem.tie_break(node)
+    }
+}
+
+/// Appends `leg` to `path`, where `leg` starts at the node `path` ends at.
+fn append<C: Zero + Copy>(path: &mut Path<Pos, C>, leg: Path<Pos, C>) {
+    let base = path.cost;
+    path.nodes.extend_from_slice(&leg.nodes[1..]);
+    path.costs.extend(leg.costs[1..].iter().map(|&cost| base + cost));
+    path.jumps.extend(leg.jumps);
+    path.indices.extend_from_slice(&leg.indices[1..]);
+    path.cost = base + leg.cost;
+}
+
+/// A wall along x = 1000 that can only be crossed through the `gaps`, given as ranges along y.
+#[cfg(test)]
+fn wall_with_gaps(gaps: &[(f32, f32)]) -> impl Fn(Vec2, Vec2) -> bool + '_ {
+    move |from, to| {
+        let (dx_from, dx_to) = (from.x - 1000.0, to.x - 1000.0);
+        if 0.0 < dx_from * dx_to {
+            return true;
+        }
+
+        // Where the move touches the wall.
+        let t = if dx_from == dx_to { 0.0 } else { dx_from / (dx_from - dx_to) };
+        let y = from.y + (to.y - from.y) * t;
+        gaps.iter().any(|&(beg, end)| beg <= y && y <= end)
+    }
+}
+
+#[cfg(test)]
+struct Field<F> {
+    is_clear: F,
+    goal: Pos,
+}
+
+#[cfg(test)]
+impl<F: Fn(Vec2, Vec2) -> bool> SearchProblem for Field<F> {
+    type Node = Pos;
+    type Cost = Cost;
+    type Successors = Vec<(Pos, Cost)>;
+
+    fn successors(&mut self, node: &Pos) -> Self::Successors {
+        node.successors(0.5, 50.0).into_iter().map(|(next, _)| (next, self.movement_cost(node, &next))).collect()
+    }
+
+    fn is_valid_move(&mut self, from: &Pos, to: &Pos) -> bool {
+        (self.is_clear)(from.vec(), to.vec())
+    }
+
+    // The time taken, in steps, so that waiting isn't free.
+    fn movement_cost(&mut self, from: &Pos, to: &Pos) -> Cost {
+        ((to.time() - from.time()) / 0.5).into()
+    }
+
+    fn heuristic(&mut self, node: &Pos) -> Cost {
+        (node.dist(&self.goal) / 50.0).into()
+    }
+
+    fn success(&mut self, node: &Pos) -> bool {
+        node.is_same_pos(&self.goal, 50.0)
+    }
+
+    fn jump_check(&mut self, from: &Pos, skip: &Pos, to: &Pos) -> Option<Pos> {
+        Pos::jump_calc(from, skip, to, 100.0)
+    }
+}
+
+#[cfg(test)]
+fn crosses_at(route: &[Vec2]) -> Option<f32> {
+    route.windows(2).find(|pair| pair[0].x < 1000.0 && 1000.0 <= pair[1].x).map(|pair| pair[1].y)
+}
+
+#[test]
+fn plan_goes_through_gap() {
+    let is_clear = wall_with_gaps(&[(1500.0, 1600.0)]);
+    let mut hierarchy = Hierarchy::new(Vec2::zero(), Vec2::new(2000.0, 2000.0), 50.0, 10);
+
+    let route = hierarchy.plan(&is_clear, Vec2::new(100.0, 100.0), Vec2::new(1900.0, 100.0)).unwrap();
+
+    assert_eq!(route.first(), Some(&Vec2::new(100.0, 100.0)));
+    assert_eq!(route.last(), Some(&Vec2::new(1900.0, 100.0)));
+    assert!(crosses_at(&route).is_some_and(|y| (1500.0..=1600.0).contains(&y)));
+}
+
+#[test]
+fn plan_is_rebuilt_after_invalidation() {
+    let mut hierarchy = Hierarchy::new(Vec2::zero(), Vec2::new(2000.0, 2000.0), 50.0, 10);
+    let (start, goal) = (Vec2::new(100.0, 100.0), Vec2::new(1900.0, 100.0));
+
+    let route = hierarchy.plan(wall_with_gaps(&[(1500.0, 1600.0)]), start, goal).unwrap();
+    assert!(crosses_at(&route).is_some_and(|y| 1500.0 <= y));
+
+    // Until the clusters along the wall are invalidated, the old gap is still used.
+    let moved = [(200.0, 300.0)];
+    let route = hierarchy.plan(wall_with_gaps(&moved), start, goal).unwrap();
+    assert!(crosses_at(&route).is_some_and(|y| 1500.0 <= y));
+
+    hierarchy.invalidate(Vec2::new(900.0, 0.0), Vec2::new(1100.0, 2000.0));
+    let route = hierarchy.plan(wall_with_gaps(&moved), start, goal).unwrap();
+    assert!(crosses_at(&route).is_some_and(|y| (200.0..=300.0).contains(&y)));
+
+    // Without any gap at all, there's no route.
+    hierarchy.invalidate(Vec2::new(900.0, 0.0), Vec2::new(1100.0, 2000.0));
+    assert_eq!(hierarchy.plan(wall_with_gaps(&[]), start, goal), None);
+}
+
+#[test]
+fn find_refines_route() {
+    let is_clear = wall_with_gaps(&[(1500.0, 1600.0)]);
+    let mut hierarchy = Hierarchy::new(Vec2::zero(), Vec2::new(2000.0, 2000.0), 50.0, 10);
+
+    let goal = Pos::new(1900.0, 100.0, 0.0);
+    let mut field = Field { is_clear: &is_clear, goal };
+    let distance = |from: &Pos, to: &Pos| Cost::from(from.dist(to) / 50.0);
+
+    let start = Pos::new(100.0, 100.0, 0.0);
+    let budget = Budget { max_expansions: Some(10000), ..Budget::default() };
+    let path = hierarchy.find(&is_clear, &mut field, distance, start, goal.vec(), 100.0, &budget).unwrap();
+    let nodes = &path.nodes;
+
+    assert_eq!(nodes.first(), Some(&start));
+    assert!(nodes.last().unwrap().is_same_pos(&goal, 50.0));
+    assert!(nodes.windows(2).all(|pair| is_clear(pair[0].vec(), pair[1].vec())));
+    assert!(nodes.windows(2).all(|pair| pair[0].time() < pair[1].time()));
+
+    let steps = (nodes.last().unwrap().time() - start.time()) / 0.5;
+    assert!((path.cost.0 - steps).abs() < 0.01);
+
+    // The legs add up to a single path.
+    assert_eq!((path.costs.len(), path.jumps.len(), path.indices.len()), (nodes.len(), nodes.len() - 1, nodes.len()));
+    assert_eq!(path.costs.last(), Some(&path.cost));
+    for segment in path.segments() {
+        assert!((segment.cost.0 - field.movement_cost(&segment.from, &segment.to).0).abs() < 0.01);
+    }
+}
+
+#[test]
+fn find_times_waypoints_by_arrival() {
+    let is_clear = wall_with_gaps(&[(1500.0, 1600.0)]);
+    let mut hierarchy = Hierarchy::new(Vec2::zero(), Vec2::new(2000.0, 2000.0), 50.0, 10);
+
+    let goal = Pos::new(1900.0, 100.0, 0.0);
+    let mut field = Field { is_clear: &is_clear, goal };
+    let mut waypoints = Vec::new();
+    let distance = |from: &Pos, to: &Pos| {
+        if !waypoints.contains(to) {
+            waypoints.push(*to);
+        }
+        Cost::from(from.dist(to) / 50.0)
+    };
+
+    let start = Pos::new(100.0, 100.0, 3.0);
+    let budget = Budget { max_expansions: Some(10000), ..Budget::default() };
+    let path = hierarchy.find(&is_clear, &mut field, distance, start, goal.vec(), 100.0, &budget).unwrap();
+
+    // The first leg starts at the start, and every leg after it where the one before it ended.
+    assert!(waypoints.len() > 1);
+    assert_eq!(waypoints[0].time(), start.time() + start.dist(&waypoints[0]) / 100.0);
+
+    for pair in waypoints.windows(2) {
+        let leg_start = path.nodes.iter().find(|node| node.is_same_pos(&pair[0], 50.0)).unwrap();
+        assert!((pair[1].time() - (leg_start.time() + leg_start.dist(&pair[1]) / 100.0)).abs() < 1e-3);
+    }
+}
+
+#[test]
+fn rebuild_matches_graph_built_from_scratch() {
+    let sorted = |hierarchy: &Hierarchy| {
+        let mut graph = hierarchy.graph.iter().map(|(&cell, edges)| (cell, edges.clone())).collect::<Vec<_>>();
+        graph.iter_mut().for_each(|(_, edges)| edges.sort());
+        graph.sort();
+        graph
+    };
+
+    let (start, goal) = (Vec2::new(100.0, 100.0), Vec2::new(1900.0, 100.0));
+    let mut rebuilt = Hierarchy::new(Vec2::zero(), Vec2::new(2000.0, 2000.0), 50.0, 10);
+    rebuilt.plan(wall_with_gaps(&[(1500.0, 1600.0), (700.0, 800.0)]), start, goal).unwrap();
+
+    // Only the clusters along the wall are rebuilt, and the ones right next to them.
+    let calls = std::cell::Cell::new(0);
+    let moved = wall_with_gaps(&[(200.0, 300.0)]);
+    let counted = |from, to| {
+        calls.set(calls.get() + 1);
+        moved(from, to)
+    };
+
+    rebuilt.invalidate(Vec2::new(900.0, 0.0), Vec2::new(1100.0, 2000.0));
+    rebuilt.plan(counted, start, goal).unwrap();
+
+    let mut fresh = Hierarchy::new(Vec2::zero(), Vec2::new(2000.0, 2000.0), 50.0, 10);
+    let rebuilt_calls = calls.replace(0);
+    fresh.plan(counted, start, goal).unwrap();
+
+    assert_eq!(sorted(&rebuilt), sorted(&fresh));
+    assert!(rebuilt_calls < calls.get());
+}
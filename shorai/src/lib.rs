@@ -1,9 +1,20 @@
 pub mod math;
 
+pub mod agents;
+pub mod alternatives;
+pub mod anytime;
+pub mod bidirectional;
+pub mod cache;
 pub mod geometry;
+pub mod goals;
+pub mod hierarchy;
 pub mod missile;
+pub mod parallel;
 pub mod pathfind;
 pub mod pos;
+pub mod pursuit;
+pub mod replan;
+pub mod smooth;
 
 pub type FxIndexMap<K, V> = indexmap::IndexMap<K, V, std::hash::BuildHasherDefault<rustc_hash::FxHasher>>;
 pub type Cost = ordered_float::OrderedFloat<f32>;
@@ -43,6 +43,35 @@ pub fn collides_within_time(
     }
 }
 
+/// Like [`collides_within_time`], but returns how long it takes for the two to start touching.
+/// Returns `None` if `time` is negative or NaN.
+#[inline]
+pub fn collision_time_within(
+    p_lhs: Vec2,
+    p_rhs: Vec2,
+    v_lhs: Vec2,
+    v_rhs: Vec2,
+    radius_sq: f32,
+    time: f32,
+) -> Option<f32> {
+    if time.is_nan() || time < 0.0 || !collides_within_time(p_lhs, p_rhs, v_lhs, v_rhs, radius_sq, time) {
+        return None;
+    }
+
+    if (p_lhs - p_rhs).mag_sq() < radius_sq {
+        return Some(0.0);
+    }
+
+    // The first root of the same quadratic, which is when the distance first drops to the radius.
+    let (v_dlt, p_dlt) = (v_lhs - v_rhs, p_lhs - p_rhs);
+    let (c0, c1, c2) = (v_dlt.mag_sq(), v_dlt.dot(p_dlt), p_dlt.mag_sq() - radius_sq);
+
+    let p = c1 / c0;
+    let d = p * p - c2 / c0;
+
+    Some((-p - d.max(0.0).sqrt()).max(0.0).min(time))
+}
+
 #[inline(always)]
 pub fn absdiff(x: f32, y: f32) -> f32 {
     if x < y {
@@ -51,3 +80,13 @@ pub fn absdiff(x: f32, y: f32) -> f32 {
         x - y
     }
 }
+
+#[test]
+fn collision_time_within_rejects_negative_time() {
+    let (p_lhs, p_rhs, v_lhs) = (Vec2::new(0.0, 0.0), Vec2::new(100.0, 0.0), Vec2::new(10.0, 0.0));
+
+    let time = collision_time_within(p_lhs, p_rhs, v_lhs, Vec2::zero(), 1.0, 20.0);
+    assert!(time.is_some_and(|time| (time - 9.9).abs() < 1e-3));
+    assert_eq!(collision_time_within(p_lhs, p_rhs, v_lhs, Vec2::zero(), 1.0, -1.0), None);
+    assert_eq!(collision_time_within(p_lhs, p_rhs, v_lhs, Vec2::zero(), 1.0, f32::NAN), None);
+}
@@ -22,12 +22,25 @@ impl Missile {
         let offset = target - origin;
         let distance = offset.mag();
 
-        let time_moving = distance / speed;
-        let time_offset = offset / time_moving;
+        // A missile that doesn't go anywhere is done moving right away and doesn't move either, even
+        // if its speed is 0 as well, rather than taking 0 / 0 seconds at a speed of 0 / 0.
+        let time_moving = if distance == 0.0 { 0.0 } else { distance / speed };
+        let time_offset = if time_moving > 0.0 { offset / time_moving } else { Vec2::zero() };
 
         Missile { origin, target, radius, time_offset, time_beg: spawn_time, time_end: spawn_time + time_moving }
     }
 
+    /// Where the missile is at `time`. Before it spawns it's at its origin, and after it's reached
+    /// its target it stays there.
+    #[must_use]
+    pub fn position(&self, time: f32) -> Vec2 {
+        // This intentionally isn't `clamp`, which panics if the missile ends before it begins, such
+        // as with a negative speed, or if either end is NaN.
+        #[allow(clippy::manual_clamp)]
+        let time = time.max(self.time_beg).min(self.time_end);
+        self.origin + self.time_offset * (time - self.time_beg)
+    }
+
     #[must_use]
     pub fn get_pos_range(&self, time: Range<f32>) -> Option<(Pos, Pos)> {
         let is_alive = self.time_beg <= time.end && time.start <= self.time_end;
@@ -58,7 +71,7 @@ impl Missile {
 
     #[must_use]
     pub fn overlaps(&self, smear_from: f32, pos: Pos, pawn_size: f32) -> bool {
-        self.get_pos_range(smear_from..pos.time()).map_or(false, |(beg, end)| {
+        self.get_pos_range(smear_from..pos.time()).is_some_and(|(beg, end)| {
             Line(beg.vec(), end.vec()).dist_to_point_sq(pos.vec()) < (self.radius + pawn_size).powi(2)
         })
     }
@@ -261,3 +274,21 @@ fn missile_collides_with_static_object() {
     assert!(!missile.collides(pos, pos_v, 28.0..29.0, 0.0));
     assert!(!missile.collides(pos, pos_v, 31.0..32.0, 0.0));
 }
+
+#[test]
+fn missile_without_distance_stays_in_place() {
+    let missile = Missile::new(5.0, Vec2::new(10.0, 20.0), Vec2::new(10.0, 20.0), 1.0, 0.0);
+
+    assert_eq!((missile.time_beg, missile.time_end), (5.0, 5.0));
+    assert_eq!(missile.position(0.0), Vec2::new(10.0, 20.0));
+    assert_eq!(missile.position(5.0), Vec2::new(10.0, 20.0));
+    assert_eq!(missile.position(10.0), Vec2::new(10.0, 20.0));
+}
+
+#[test]
+fn missile_with_negative_speed_has_position() {
+    let missile = Missile::new(5.0, Vec2::new(10.0, 20.0), Vec2::new(30.0, 20.0), 1.0, -10.0);
+
+    assert!(missile.time_end < missile.time_beg);
+    assert_eq!(missile.position(0.0), missile.position(10.0));
+}
@@ -0,0 +1,589 @@
+//! A multithreaded search using hash-distributed A* (HDA*).
+//!
+//! Every node is owned by exactly one worker thread, picked by hashing the node. Each worker runs
+//! its own copy of the search over the nodes it owns, and sends the moves it finds to nodes owned
+//! by other workers over to them instead of handling them itself. Since nothing but the incumbent
+//! goal is shared, and workers only look at it again once it has changed, they hardly ever wait on
+//! each other, and the search scales with the number of cores as long as expanding a node is
+//! expensive enough to make up for the messages.
+//!
+//! The moves work the same way as in [`pathfind`](crate::pathfind), including lazily validated
+//! jumps and their fallbacks. The only difference is that nodes are identified by their value
+//! rather than their index, since the parent of a node usually lives in another worker.
+//!
+//! Unlike a sequential search, the first goal a worker reaches isn't necessarily the cheapest one,
+//! since the other workers may still be working on cheaper nodes. So instead of stopping right
+//! away, the search keeps going until no worker has a node left that could lead to a cheaper goal.
+//! That way the result is as good as the one a sequential search would find.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::hash::{BuildHasher, BuildHasherDefault, Hash};
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering::SeqCst};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+
+use num_traits::Zero;
+use rustc_hash::FxHasher;
+
+use crate::pathfind::{Budget, FindError, Limit, Path, SearchProblem};
+use crate::FxIndexMap;
+
+#[cfg(test)]
+use crate::pathfind::GridProblem;
+
+/// Like [`pathfind::find_bounded`](crate::pathfind::find_bounded), but spreads the search over
+/// `threads` worker threads.
+///
+/// Every worker gets its own clone of `problem`. The budget is shared by all of the workers, so
+/// `max_expansions` and `max_visited` limit the total over all of them.
+///
+/// Since the nodes of the path are spread over the workers, the `indices` of the path are the
+/// indices of the nodes in the visited map of whichever worker owns them. The costs are worked out
+/// from the moves of the path with [`SearchProblem::movement_cost`] once the search is done.
+pub fn find_parallel<P, N, C>(
+    problem: &P,
+    start: N,
+    budget: &Budget,
+    threads: NonZeroUsize,
+) -> Result<Path<N, C>, FindError>
+where
+    P: SearchProblem<Node = N, Cost = C> + Clone + Send,
+    N: Eq + Hash + Copy + Send,
+    C: Zero + Ord + Copy + Send,
+{
+    let threads = threads.get();
+    let (senders, receivers): (Vec<_>, Vec<_>) = (0..threads).map(|_| mpsc::channel()).unzip();
+
+    let shared = Shared {
+        budget,
+        // Every worker starts out active, and the start node is in flight.
+        work: AtomicUsize::new(threads + 1),
+        expansions: AtomicUsize::new(0),
+        visited: AtomicUsize::new(0),
+        stop: AtomicBool::new(false),
+        limit: Mutex::new(None),
+        incumbent: Mutex::new(None),
+        improved: AtomicUsize::new(0),
+    };
+
+    let root = Offer { node: start, parent: None, parent_cost: Zero::zero(), cost: Zero::zero(), fallback: None };
+    senders[owner(&start, threads)].send(Message::Offer(root)).unwrap();
+
+    let visited = std::thread::scope(|scope| {
+        let workers = receivers
+            .into_iter()
+            .enumerate()
+            .map(|(id, inbox)| {
+                let mut worker = Worker {
+                    id,
+                    problem: problem.clone(),
+                    shared: &shared,
+                    inbox,
+                    outboxes: senders.clone(),
+                    pending: BinaryHeap::new(),
+                    visited: FxIndexMap::default(),
+                    idle: false,
+                    bound: None,
+                    seen: 0,
+                };
+
+                scope.spawn(move || {
+                    worker.run();
+                    worker.visited
+                })
+            })
+            .collect::<Vec<_>>();
+
+        workers.into_iter().map(|worker| worker.join().unwrap()).collect::<Vec<_>>()
+    });
+
+    if let Some(limit) = shared.limit.into_inner().unwrap() {
+        return Err(FindError::BudgetExceeded(limit));
+    }
+
+    let Some((_, goal)) = shared.incumbent.into_inner().unwrap() else {
+        return Err(FindError::Exhausted);
+    };
+
+    // The parents of a node usually live in other workers, so we have to look them up in whichever
+    // worker owns them. Parents are always cheaper than their children, so this can't loop.
+    let lookup = |node: &N| visited[owner(node, threads)].get_full(node).unwrap();
+    let mut indices = Vec::new();
+    let mut nodes = vec![goal];
+
+    loop {
+        let (index, _, entry) = lookup(&nodes[nodes.len() - 1]);
+        indices.push(index);
+
+        let Some(parent) = entry.valid.and_then(|valid| valid.parent) else {
+            break;
+        };
+        nodes.push(parent);
+    }
+
+    nodes.reverse();
+    indices.reverse();
+
+    // Other workers may have found cheaper ways to the parents since the children were reached
+    // through them, so the costs the workers recorded don't necessarily add up along the path.
+    let mut problem = problem.clone();
+    let mut costs = vec![C::zero()];
+    for pair in nodes.windows(2) {
+        costs.push(costs[costs.len() - 1] + problem.movement_cost(&pair[0], &pair[1]));
+    }
+
+    let jumps = nodes[1..].iter().map(|node| lookup(node).2.valid.is_some_and(|valid| valid.jump)).collect();
+    Ok(Path { cost: costs[costs.len() - 1], nodes, partial: false, costs, jumps, indices })
+}
+
+fn owner<N: Hash>(node: &N, workers: usize) -> usize {
+    let hash = BuildHasherDefault::<FxHasher>::default().hash_one(node);
+    // Maps the hash onto the workers using its high bits, which FxHash mixes better than the low ones.
+    ((u128::from(hash) * workers as u128) >> 64) as usize
+}
+
+/// The state shared by all workers.
+struct Shared<'a, N, C> {
+    budget: &'a Budget,
+    // The number of active workers plus the number of messages in flight. When this reaches zero,
+    // every worker is out of useful nodes and nothing new can show up, so the search is done.
+    work: AtomicUsize,
+    expansions: AtomicUsize,
+    visited: AtomicUsize,
+    stop: AtomicBool,
+    limit: Mutex<Option<Limit>>,
+    // The cheapest goal found so far.
+    incumbent: Mutex<Option<(C, N)>>,
+    // The number of times the incumbent has improved, which lets workers tell whether they have to
+    // look at it again without locking it.
+    improved: AtomicUsize,
+}
+
+/// What workers send each other.
+enum Message<N, C> {
+    Offer(Offer<N, C>),
+    // The search is over, which wakes up the workers that are waiting for more work.
+    Finish,
+}
+
+/// A move to a node, sent to the worker owning the node.
+struct Offer<N, C> {
+    node: N,
+    parent: Option<N>,
+    parent_cost: C,
+    cost: C,
+    fallback: Option<Box<Offer<N, C>>>,
+}
+
+#[derive(Copy, Clone)]
+struct Move<N, C> {
+    parent: Option<N>,
+    // Kept around so that jumps from the parent can be costed without asking its owner.
+    parent_cost: C,
+    cost: C,
+    jump: bool,
+}
+
+struct Entry<N, C> {
+    // The cheapest move here we know of, which may not have been validated yet.
+    current: Move<N, C>,
+    // The cheapest move here that was validated. This is the one the node was expanded through,
+    // and the one we go back to if a cheaper move turns out to be invalid.
+    valid: Option<Move<N, C>>,
+    checked: bool,
+}
+
+struct Worker<'a, P: SearchProblem> {
+    id: usize,
+    problem: P,
+    shared: &'a Shared<'a, P::Node, P::Cost>,
+    inbox: Receiver<Message<P::Node, P::Cost>>,
+    outboxes: Vec<Sender<Message<P::Node, P::Cost>>>,
+    pending: BinaryHeap<Pending<P::Node, P::Cost>>,
+    visited: FxIndexMap<P::Node, Entry<P::Node, P::Cost>>,
+    // Whether this worker has given up its share of `Shared::work`.
+    idle: bool,
+    // The cost of the incumbent as of when it had last improved `seen` times.
+    bound: Option<P::Cost>,
+    seen: usize,
+}
+
+impl<P: SearchProblem> Worker<'_, P> {
+    fn run(&mut self) {
+        while !self.shared.stop.load(SeqCst) {
+            while let Ok(message) = self.inbox.try_recv() {
+                match message {
+                    Message::Offer(offer) => self.receive(offer),
+                    Message::Finish => return,
+                }
+            }
+
+            if let Some(pending) = self.pop() {
+                self.process(pending);
+                continue;
+            }
+
+            // Nothing useful left to do here, so we give up our share of the work and wait for more.
+            // If nobody else has anything left either, the search is over, and the others are woken
+            // up to leave as well. Receiving an offer always makes us active again, so we're never
+            // already idle here.
+            self.idle = true;
+            if self.shared.work.fetch_sub(1, SeqCst) == 1 {
+                self.finish();
+                return;
+            }
+
+            match self.inbox.recv() {
+                Ok(Message::Offer(offer)) => self.receive(offer),
+                Ok(Message::Finish) | Err(_) => return,
+            }
+        }
+    }
+
+    /// Wakes up every worker to leave, since the search is over.
+    fn finish(&self) {
+        for outbox in &self.outboxes {
+            // The receiving end only goes away once its worker has left.
+            let _ = outbox.send(Message::Finish);
+        }
+    }
+
+    /// Pops the next node worth looking at, which excludes any that can't lead to a goal cheaper
+    /// than the one we already have.
+    fn pop(&mut self) -> Option<Pending<P::Node, P::Cost>> {
+        let improved = self.shared.improved.load(SeqCst);
+        if improved != self.seen {
+            self.seen = improved;
+            self.bound = self.shared.incumbent.lock().unwrap().as_ref().map(|&(cost, _)| cost);
+        }
+
+        match self.pending.peek() {
+            Some(pending) if self.bound.is_none_or(|bound| pending.estimated_cost < bound) => self.pending.pop(),
+            _ => None,
+        }
+    }
+
+    fn receive(&mut self, offer: Offer<P::Node, P::Cost>) {
+        // Become active before the message stops counting towards the work, so that the work
+        // can't drop to zero in between.
+        if self.idle {
+            self.idle = false;
+            self.shared.work.fetch_add(1, SeqCst);
+        }
+
+        self.add_pending(offer);
+        self.shared.work.fetch_sub(1, SeqCst);
+    }
+
+    fn send(&mut self, offer: Offer<P::Node, P::Cost>) {
+        match owner(&offer.node, self.outboxes.len()) {
+            id if id == self.id => self.add_pending(offer),
+            id => {
+                self.shared.work.fetch_add(1, SeqCst);
+                // The receiving end only goes away once the search is over.
+                let _ = self.outboxes[id].send(Message::Offer(offer));
+            }
+        }
+    }
+
+    fn add_pending(&mut self, offer: Offer<P::Node, P::Cost>) {
+        let Offer { node, parent, parent_cost, cost, fallback } = offer;
+        let current = Move { parent, parent_cost, cost, jump: fallback.is_some() };
+
+        match self.visited.get_mut(&node) {
+            // A way here that's at least as cheap and known to be valid leaves nothing for this one
+            // to add. One that hasn't been validated yet may still be rejected though, so this one
+            // is kept around until then, along with its fallback.
+            Some(entry) if entry.current.cost <= cost && entry.checked => return,
+            Some(entry) if entry.current.cost <= cost => {}
+            Some(entry) => {
+                entry.current = current;
+                entry.checked = false;
+            }
+            None => {
+                // The start node is the only one without a parent, and it doesn't need validating.
+                let valid = parent.is_none().then_some(current);
+                self.visited.insert(node, Entry { current, valid, checked: valid.is_some() });
+                self.shared.visited.fetch_add(1, SeqCst);
+            }
+        }
+
+        let estimated_cost = cost + self.problem.heuristic(&node);
+        self.pending.push(Pending { estimated_cost, cost, node, parent, parent_cost, fallback });
+    }
+
+    fn process(
+        &mut self,
+        Pending { cost, node: p0_node, parent, parent_cost, fallback, .. }: Pending<P::Node, P::Cost>,
+    ) {
+        // The cheaper way here that replaced this one since it was pushed may have been rejected in
+        // the meantime, leaving only a more expensive way or none at all. This one is still worth
+        // considering then, so it's offered again rather than dropped along with its fallback.
+        let Some(entry) = self.visited.get_mut(&p0_node).filter(|entry| entry.current.cost <= cost) else {
+            self.add_pending(Offer { node: p0_node, parent, parent_cost, cost, fallback });
+            return;
+        };
+
+        // A cheaper way here was validated since this was pushed, or an equally cheap one was already
+        // expanded. The start node is the only one that's valid before it's expanded.
+        if entry.current.cost != cost || (entry.checked && entry.current.parent.is_some()) {
+            return;
+        }
+
+        // Equally cheap ways here may be pending at the same time, while the entry only holds one of
+        // them. The one that's validated has to be the one that was popped, so that its fallback is
+        // the one that's used if it's rejected.
+        if !entry.checked {
+            entry.current = Move { parent, parent_cost, cost, jump: fallback.is_some() };
+        }
+
+        let Move { parent: p1_node, parent_cost: p1_cost, .. } = entry.current;
+
+        if !entry.checked {
+            let p1 = p1_node.unwrap();
+            if !self.problem.is_valid_move(&p1, &p0_node) {
+                // Unlike the sequential search we go back to the last valid way here, or forget
+                // about the node entirely if there isn't one. That way the fallback can still get
+                // here if it happens to end at the same node.
+                let entry = self.visited.get_mut(&p0_node).unwrap();
+                match entry.valid {
+                    Some(valid) => {
+                        entry.current = valid;
+                        entry.checked = true;
+                    }
+                    None => {
+                        self.visited.swap_remove(&p0_node);
+                        self.shared.visited.fetch_sub(1, SeqCst);
+                    }
+                }
+
+                if let Some(fb) = fallback {
+                    self.send(*fb);
+                }
+                return;
+            }
+
+            let entry = self.visited.get_mut(&p0_node).unwrap();
+            entry.valid = Some(entry.current);
+            entry.checked = true;
+        }
+
+        if self.problem.success(&p0_node) {
+            let mut incumbent = self.shared.incumbent.lock().unwrap();
+            if incumbent.as_ref().is_none_or(|&(best, _)| cost < best) {
+                *incumbent = Some((cost, p0_node));
+                self.shared.improved.fetch_add(1, SeqCst);
+            }
+            return;
+        }
+
+        let expansions = self.shared.expansions.fetch_add(1, SeqCst);
+        if let Some(limit) = self.shared.budget.exceeded(expansions, self.shared.visited.load(SeqCst)) {
+            self.shared.limit.lock().unwrap().get_or_insert(limit);
+            self.shared.stop.store(true, SeqCst);
+            self.finish();
+            return;
+        }
+
+        for (node, move_cost) in self.problem.successors(&p0_node) {
+            let normal =
+                Offer { node, parent: Some(p0_node), parent_cost: cost, cost: cost + move_cost, fallback: None };
+
+            let jump = p1_node.and_then(|p1| Some((p1, self.problem.jump_check(&p1, &p0_node, &node)?)));
+            let offer = match jump {
+                Some((p1, jump_node)) => Offer {
+                    node: jump_node,
+                    parent: Some(p1),
+                    parent_cost: p1_cost,
+                    cost: p1_cost + self.problem.movement_cost(&p1, &jump_node),
+                    fallback: Some(Box::new(normal)),
+                },
+                None => normal,
+            };
+
+            self.send(offer);
+        }
+    }
+}
+
+struct Pending<N, C> {
+    estimated_cost: C,
+    cost: C,
+    node: N,
+    parent: Option<N>,
+    parent_cost: C,
+    fallback: Option<Box<Offer<N, C>>>,
+}
+
+impl<N, C: Eq> Eq for Pending<N, C> {}
+impl<N, C: PartialEq> PartialEq for Pending<N, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.estimated_cost == other.estimated_cost && self.cost == other.cost
+    }
+}
+
+impl<N, C: Ord> PartialOrd for Pending<N, C> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<N, C: Ord> Ord for Pending<N, C> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match other.estimated_cost.cmp(&self.estimated_cost) {
+            Ordering::Equal => self.cost.cmp(&other.cost),
+            s => s,
+        }
+    }
+}
+
+#[test]
+fn find_parallel_matches_sequential_cost() {
+    let mut problem = GridProblem::rooms((19, 0));
+    let budget = Budget::default();
+    let expected = crate::pathfind::SearchContext::new().search(&mut problem, (-19, 0), &budget).unwrap().cost;
+
+    for threads in [1, 2, 4] {
+        let threads = NonZeroUsize::new(threads).unwrap();
+        let path = find_parallel(&problem, (-19, 0), &budget, threads).unwrap();
+
+        assert_eq!(path.cost, expected);
+        assert_eq!((path.nodes.first(), path.nodes.last()), (Some(&(-19, 0)), Some(&(19, 0))));
+        assert!(path.nodes.windows(2).all(|w| problem.is_valid_move(&w[0], &w[1])));
+    }
+}
+
+#[test]
+fn find_parallel_exhausts_without_goal() {
+    let problem = GridProblem::rooms((40, 0));
+    let threads = NonZeroUsize::new(3).unwrap();
+
+    assert_eq!(find_parallel(&problem, (0, 0), &Budget::default(), threads), Err(FindError::Exhausted));
+}
+
+#[test]
+fn find_parallel_stops_at_expansion_limit() {
+    let problem = GridProblem::rooms((19, 0));
+    let budget = Budget { max_expansions: Some(10), ..Budget::default() };
+    let threads = NonZeroUsize::new(2).unwrap();
+
+    assert_eq!(find_parallel(&problem, (-19, 0), &budget, threads), Err(FindError::BudgetExceeded(Limit::Expansions)));
+}
+
+#[test]
+fn find_parallel_takes_valid_jumps() {
+    let mut problem = GridProblem::rooms((19, 0)).with_jumps();
+    let threads = NonZeroUsize::new(4).unwrap();
+    let path = find_parallel(&problem, (-19, 0), &Budget::default(), threads).unwrap();
+
+    assert_eq!((path.nodes.first(), path.nodes.last()), (Some(&(-19, 0)), Some(&(19, 0))));
+    assert!(path.nodes.windows(2).all(|w| problem.is_valid_move(&w[0], &w[1])));
+    assert_eq!((path.costs.len(), path.costs.last()), (path.nodes.len(), Some(&path.cost)));
+    assert!(path.jumps.contains(&true));
+}
+
+// Two ways from 's' to 'd', through 'a' and 'b', where only the one through 'b' is open. Both offer
+// the same jump from 's' to 'd', which is blocked.
+#[cfg(test)]
+#[derive(Clone)]
+struct Diamond;
+
+#[cfg(test)]
+impl SearchProblem for Diamond {
+    type Node = char;
+    type Cost = crate::Cost;
+    type Successors = Vec<(char, crate::Cost)>;
+
+    fn successors(&mut self, node: &char) -> Self::Successors {
+        match node {
+            's' => vec![('a', 0.9.into()), ('b', 1.0.into())],
+            'a' | 'b' => vec![('d', 1.0.into())],
+            _ => vec![],
+        }
+    }
+
+    fn is_valid_move(&mut self, from: &char, to: &char) -> bool {
+        !matches!((from, to), ('s', 'd') | ('a', 'd'))
+    }
+
+    fn movement_cost(&mut self, from: &char, to: &char) -> crate::Cost {
+        match (from, to) {
+            ('s', 'a') => 0.9.into(),
+            ('s', 'd') => 1.5.into(),
+            _ => 1.0.into(),
+        }
+    }
+
+    fn heuristic(&mut self, _: &char) -> crate::Cost {
+        0.0.into()
+    }
+
+    fn success(&mut self, node: &char) -> bool {
+        *node == 'd'
+    }
+
+    fn jump_check(&mut self, _: &char, _: &char, to: &char) -> Option<char> {
+        Some(*to)
+    }
+}
+
+#[test]
+fn find_parallel_keeps_equally_cheap_jumps_until_validated() {
+    // The jump through 'a' comes first and falls back to a blocked move, so 'd' can only be reached
+    // through the fallback of the jump through 'b'.
+    let path = find_parallel(&Diamond, 's', &Budget::default(), NonZeroUsize::new(1).unwrap());
+    assert_eq!(path.map(|path| path.nodes), Ok(vec!['s', 'b', 'd']));
+}
+
+// Three ways from 's' to 'd' that cost the same, through 'a', 'b' and 'c', where the moves to 'd'
+// from the ones given are blocked. There are no jumps.
+#[cfg(test)]
+#[derive(Clone)]
+struct Split(&'static [char]);
+
+#[cfg(test)]
+impl SearchProblem for Split {
+    type Node = char;
+    type Cost = crate::Cost;
+    type Successors = Vec<(char, crate::Cost)>;
+
+    fn successors(&mut self, node: &char) -> Self::Successors {
+        match node {
+            's' => vec![('a', 1.0.into()), ('b', 1.0.into()), ('c', 1.0.into())],
+            'a' | 'b' | 'c' => vec![('d', 1.0.into())],
+            _ => vec![],
+        }
+    }
+
+    fn is_valid_move(&mut self, from: &char, to: &char) -> bool {
+        !(self.0.contains(from) && *to == 'd')
+    }
+
+    fn movement_cost(&mut self, _: &char, _: &char) -> crate::Cost {
+        1.0.into()
+    }
+
+    fn heuristic(&mut self, _: &char) -> crate::Cost {
+        0.0.into()
+    }
+
+    fn success(&mut self, node: &char) -> bool {
+        *node == 'd'
+    }
+
+    fn jump_check(&mut self, _: &char, _: &char, _: &char) -> Option<char> {
+        None
+    }
+}
+
+#[test]
+fn find_parallel_validates_the_move_it_pops() {
+    // Every way to 'd' is pending at the same cost, so whichever is popped has to be the one that's
+    // validated, no matter which of them the entry for 'd' currently holds.
+    for (blocked, open) in [(&['a', 'b'], 'c'), (&['a', 'c'], 'b'), (&['b', 'c'], 'a')] {
+        let path = find_parallel(&Split(blocked), 's', &Budget::default(), NonZeroUsize::new(1).unwrap());
+        assert_eq!(path.map(|path| path.nodes), Ok(vec!['s', open, 'd']));
+    }
+}
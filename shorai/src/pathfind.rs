@@ -6,6 +6,8 @@
 //!
 //! - `start`:
 //!    - The start position of the path.
+//! - `budget`:
+//!    - Limits on how much work the search may do before giving up. See [`Budget`].
 //! - `initialize`:
 //!    - A function that returns an iterator over the initial nodes of the path.
 //! - `successors`:
@@ -21,6 +23,11 @@
 //!     it should be a valid metric for the given problem.
 //! - `success`:
 //!   - Called to determine if a node is considered a valid goal.
+//! - `progress`:
+//!   - Only used by the partial searches. Ranks the nodes that were reached, where lower is better,
+//!     to decide which one the best-effort path leads to if the goal can't be reached. Passing the
+//!     heuristic here picks the node closest to the goal, while passing something like a negated
+//!     time picks the path that survives the longest in time-based problems.
 //! - `jump_check`:
 //!   - Called when a jump is taken and allows making modifications to the jumped-to `N`.
 //!   
@@ -37,21 +44,1054 @@
 //!      - The node we're considering jumping from.
 //!      - The node we're considering skipping over.
 //!      - The node we're considering jumping to.
+//!
+//! Instead of passing the callbacks one by one, they can also be packaged up as a reusable type by
+//! implementing [`SearchProblem`] and searching with [`SearchContext::search`].
+//!
+//! To spread a single search over several frames, drive it step by step through a [`Search`].
 
 use indexmap::map::Entry::{Occupied, Vacant};
 use num_traits::Zero;
-use std::cmp::Ordering;
+use std::cmp::{Ordering, Reverse};
 use std::collections::BinaryHeap;
 use std::hash::Hash;
 use std::iter;
+use std::marker::PhantomData;
+use std::ops::Sub;
+use std::sync::atomic::{self, AtomicBool};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use crate::FxIndexMap;
 
-// TODO: Offer a wrapper that short tries a plain move to a goal first, avoiding pathing entirely
-// if the goal is reachable? This would save us a decent amount of time in the case where there's
-// no obstacles in the way.
+/// Limits on how much work a single search is allowed to do.
+///
+/// Every limit is optional, and the default budget is unlimited. Once a limit is hit the search
+/// stops before expanding any further nodes and reports which [`Limit`] it ran into.
+#[derive(Clone, Debug, Default)]
+pub struct Budget {
+    /// The maximum number of nodes that may be expanded.
+    pub max_expansions: Option<usize>,
+    /// The maximum number of nodes that may be held in the visited map.
+    pub max_visited: Option<usize>,
+    /// The point in time after which no more nodes will be expanded.
+    pub deadline: Option<Instant>,
+    /// Setting this flag (from anywhere, including other threads) cancels the search.
+    pub cancel: Option<Arc<AtomicBool>>,
+}
+
+/// The limit of a [`Budget`] that caused a search to stop.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Limit {
+    Expansions,
+    Visited,
+    Deadline,
+    Cancelled,
+}
+
+/// Why a search didn't find a path.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FindError {
+    /// Every reachable node was expanded without reaching a goal.
+    Exhausted,
+    /// The search was stopped early because it ran into a limit of its [`Budget`].
+    BudgetExceeded(Limit),
+    /// None of the moves passed to `initialize` were valid, so there was nothing to search from.
+    NoValidInitialMoves,
+    /// The search was given nothing to work with, such as an `initialize` without any moves.
+    InvalidInput,
+}
+
+impl std::fmt::Display for FindError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FindError::Exhausted => write!(f, "search exhausted without reaching a goal"),
+            FindError::BudgetExceeded(limit) => write!(f, "search budget exceeded: {limit:?}"),
+            FindError::NoValidInitialMoves => write!(f, "none of the initial moves are valid"),
+            FindError::InvalidInput => write!(f, "invalid search input"),
+        }
+    }
+}
+
+impl std::error::Error for FindError {}
+
+impl Budget {
+    /// A budget that only limits the search to `duration` of wall-clock time, starting now.
+    #[must_use]
+    pub fn time_slice(duration: Duration) -> Budget {
+        Budget { deadline: Some(Instant::now() + duration), ..Budget::default() }
+    }
+
+    /// Returns the first limit that has been hit, if any.
+    ///
+    /// The checks are ordered from cheapest to most expensive, which is why the deadline is last.
+    #[must_use]
+    #[inline]
+    pub fn exceeded(&self, expansions: usize, visited: usize) -> Option<Limit> {
+        if self.max_expansions.is_some_and(|max| max <= expansions) {
+            Some(Limit::Expansions)
+        } else if self.max_visited.is_some_and(|max| max < visited) {
+            Some(Limit::Visited)
+        } else if self.cancel.as_ref().is_some_and(|flag| flag.load(atomic::Ordering::Relaxed)) {
+            Some(Limit::Cancelled)
+        } else if self.deadline.is_some_and(|deadline| deadline <= Instant::now()) {
+            Some(Limit::Deadline)
+        } else {
+            None
+        }
+    }
+}
+
+/// A path found by a search, along with how it was put together.
+///
+/// Every search that finds a single path returns one of these, including the closure-based `find`
+/// functions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Path<N, C> {
+    /// The nodes of the path, starting with the start node.
+    pub nodes: Vec<N>,
+    /// The cost of taking the path.
+    pub cost: C,
+    /// Whether the path ends at the best reached node rather than at a goal.
+    pub partial: bool,
+    /// The accumulated cost of getting to each node, starting with zero for the start node.
+    pub costs: Vec<C>,
+    /// Whether the move to each node after the start node was a jump rather than a plain move to
+    /// a successor, so `jumps[i]` describes the move from `nodes[i]` to `nodes[i + 1]`.
+    pub jumps: Vec<bool>,
+    /// The index of each node in the visited map of the search. The parent of each node is the
+    /// one before it, so this is the chain of parent indices the path was rebuilt from.
+    pub indices: Vec<usize>,
+}
+
+/// A single move of a [`Path`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Segment<N, C> {
+    pub from: N,
+    pub to: N,
+    /// The cost of the move itself.
+    pub cost: C,
+    /// Whether the move was a jump rather than a plain move to a successor.
+    pub jump: bool,
+}
+
+impl<N: Copy, C: Copy + Sub<Output = C>> Path<N, C> {
+    /// Iterates over the moves of the path, in order.
+    pub fn segments(&self) -> impl Iterator<Item = Segment<N, C>> + '_ {
+        (0..self.jumps.len()).map(|i| Segment {
+            from: self.nodes[i],
+            to: self.nodes[i + 1],
+            cost: self.costs[i + 1] - self.costs[i],
+            jump: self.jumps[i],
+        })
+    }
+}
+
+/// How [`SearchContext::search_direct`] got to its path.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Route<N, C> {
+    /// A goal could be moved to straight from the start, so no search was needed.
+    Direct(Path<N, C>),
+    /// None of the goals could be moved to directly, so the path was searched for.
+    Searched(Path<N, C>),
+}
+
+impl<N, C> Route<N, C> {
+    #[must_use]
+    pub fn path(&self) -> &Path<N, C> {
+        match self {
+            Route::Direct(path) | Route::Searched(path) => path,
+        }
+    }
+
+    #[must_use]
+    pub fn into_path(self) -> Path<N, C> {
+        match self {
+            Route::Direct(path) | Route::Searched(path) => path,
+        }
+    }
+}
+
+/// A search problem packaged up as a type.
+///
+/// The methods correspond one-to-one with the callbacks described in the module documentation,
+/// so refer to that for what each of them is expected to do. The closure-based functions are
+/// implemented on top of this trait through [`FnProblem`].
+pub trait SearchProblem {
+    type Node: Eq + Hash + Copy;
+    type Cost: Zero + Ord + Copy;
+    type Successors: IntoIterator<Item = (Self::Node, Self::Cost)>;
+
+    fn successors(&mut self, node: &Self::Node) -> Self::Successors;
+    fn is_valid_move(&mut self, from: &Self::Node, to: &Self::Node) -> bool;
+    fn movement_cost(&mut self, from: &Self::Node, to: &Self::Node) -> Self::Cost;
+    fn heuristic(&mut self, node: &Self::Node) -> Self::Cost;
+    fn success(&mut self, node: &Self::Node) -> bool;
+
+    /// Defaults to never allowing a jump, which turns the search into a plain A*.
+    fn jump_check(&mut self, _from: &Self::Node, _skip: &Self::Node, _to: &Self::Node) -> Option<Self::Node> {
+        None
+    }
+
+    /// Only used by the partial searches, and defaults to the heuristic.
+    fn progress(&mut self, node: &Self::Node) -> Self::Cost {
+        self.heuristic(node)
+    }
+
+    /// Only used with [`TieBreak::Custom`], where nodes with a higher value are expanded first.
+    fn tie_break(&mut self, _node: &Self::Node) -> Self::Cost {
+        Zero::zero()
+    }
+}
+
+/// A [`SearchProblem`] made up of closures, in the same order as the closure-based functions take
+/// them.
+#[derive(Clone)]
+pub struct FnProblem<N, C, S, V, M, H, G, J> {
+    successors: S,
+    is_valid_move: V,
+    movement_cost: M,
+    heuristic: H,
+    success: G,
+    jump_check: J,
+    marker: PhantomData<fn(&N) -> C>,
+}
+
+impl<N, C, S, V, M, H, G, J> FnProblem<N, C, S, V, M, H, G, J> {
+    #[must_use]
+    pub fn new(successors: S, is_valid_move: V, movement_cost: M, heuristic: H, success: G, jump_check: J) -> Self {
+        FnProblem { successors, is_valid_move, movement_cost, heuristic, success, jump_check, marker: PhantomData }
+    }
+}
+
+impl<N, C, IterSuccessors, S, V, M, H, G, J> SearchProblem for FnProblem<N, C, S, V, M, H, G, J>
+where
+    N: Eq + Hash + Copy,
+    C: Zero + Ord + Copy,
+    IterSuccessors: IntoIterator<Item = (N, C)>,
+    S: FnMut(&N) -> IterSuccessors,
+    V: FnMut(&N, &N) -> bool,
+    M: FnMut(&N, &N) -> C,
+    H: FnMut(&N) -> C,
+    G: FnMut(&N) -> bool,
+    J: FnMut(&N, &N, &N) -> Option<N>,
+{
+    type Node = N;
+    type Cost = C;
+    type Successors = IterSuccessors;
+
+    #[inline(always)]
+    fn successors(&mut self, node: &N) -> IterSuccessors {
+        (self.successors)(node)
+    }
+
+    #[inline(always)]
+    fn is_valid_move(&mut self, from: &N, to: &N) -> bool {
+        (self.is_valid_move)(from, to)
+    }
+
+    #[inline(always)]
+    fn movement_cost(&mut self, from: &N, to: &N) -> C {
+        (self.movement_cost)(from, to)
+    }
+
+    #[inline(always)]
+    fn heuristic(&mut self, node: &N) -> C {
+        (self.heuristic)(node)
+    }
+
+    #[inline(always)]
+    fn success(&mut self, node: &N) -> bool {
+        (self.success)(node)
+    }
+
+    #[inline(always)]
+    fn jump_check(&mut self, from: &N, skip: &N, to: &N) -> Option<N> {
+        (self.jump_check)(from, skip, to)
+    }
+}
+
+/// Overrides the progress ranking of another problem, which is how the closure-based partial
+/// searches pass their `progress` closure along.
+struct WithProgress<P, F> {
+    problem: P,
+    progress: F,
+}
+
+impl<P: SearchProblem, F: FnMut(&P::Node) -> P::Cost> SearchProblem for WithProgress<P, F> {
+    type Node = P::Node;
+    type Cost = P::Cost;
+    type Successors = P::Successors;
+
+    #[inline(always)]
+    fn successors(&mut self, node: &P::Node) -> P::Successors {
+        self.problem.successors(node)
+    }
+
+    #[inline(always)]
+    fn is_valid_move(&mut self, from: &P::Node, to: &P::Node) -> bool {
+        self.problem.is_valid_move(from, to)
+    }
+
+    #[inline(always)]
+    fn movement_cost(&mut self, from: &P::Node, to: &P::Node) -> P::Cost {
+        self.problem.movement_cost(from, to)
+    }
+
+    #[inline(always)]
+    fn heuristic(&mut self, node: &P::Node) -> P::Cost {
+        self.problem.heuristic(node)
+    }
+
+    #[inline(always)]
+    fn success(&mut self, node: &P::Node) -> bool {
+        self.problem.success(node)
+    }
+
+    #[inline(always)]
+    fn jump_check(&mut self, from: &P::Node, skip: &P::Node, to: &P::Node) -> Option<P::Node> {
+        self.problem.jump_check(from, skip, to)
+    }
+
+    #[inline(always)]
+    fn progress(&mut self, node: &P::Node) -> P::Cost {
+        (self.progress)(node)
+    }
+
+    #[inline(always)]
+    fn tie_break(&mut self, node: &P::Node) -> P::Cost {
+        self.problem.tie_break(node)
+    }
+}
+
+/// Counters describing the work done by a single search.
+///
+/// Collected by searches run through a [`SearchContext`] created with
+/// [`SearchContext::with_observer`], and reset at the start of every search.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Nodes whose successors were retrieved.
+    pub expanded: usize,
+    /// Nodes pushed to the pending heap.
+    pub pushed: usize,
+    /// Heap entries that were skipped since a cheaper way to the same node had been found since.
+    pub stale: usize,
+    /// Jumps that were offered by `jump_check` and registered in place of a normal move.
+    pub jumps: usize,
+    /// Fallback moves pushed after a jump turned out to be invalid.
+    pub fallbacks: usize,
+    /// Calls to `is_valid_move`.
+    pub valid_move_checks: usize,
+    /// The largest the pending heap got.
+    pub peak_pending: usize,
+    /// The largest the visited map got.
+    pub peak_visited: usize,
+    /// Nodes dropped from the visited map to stay under the memory limit of the context.
+    pub pruned: usize,
+}
+
+/// Something that happened during a search, as reported to an [`Observer`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Event<N, C> {
+    /// A search was started from `start`.
+    Started { start: N },
+    /// `node` was pushed to the pending heap, after which the heap holds `pending` nodes and the
+    /// visited map holds `visited` nodes.
+    Pushed { node: N, parent: Option<N>, cost: C, pending: usize, visited: usize },
+    /// `node` was popped from the pending heap.
+    Popped { node: N, cost: C },
+    /// The popped `node` was skipped since a cheaper way to it has been found since it was pushed.
+    Stale { node: N },
+    /// `is_valid_move` was called for the move from `from` to `to`.
+    MoveChecked { from: N, to: N, valid: bool },
+    /// `jump_check` allowed skipping over `skip`, so the jump from `from` to `to` was registered in
+    /// place of the normal move. Whether the jump is valid isn't known until it's popped.
+    JumpOffered { from: N, skip: N, to: N },
+    /// A popped jump turned out to be a valid move.
+    JumpAccepted { from: N, to: N },
+    /// A popped jump turned out to be an invalid move.
+    JumpRejected { from: N, to: N },
+    /// The move from `from` to `to` was pushed after the jump it was a fallback for was rejected.
+    /// This is the normal move, unless the rejected jump skipped several ancestors, in which case
+    /// it's the jump from the ancestor before.
+    FallbackPushed { from: N, to: N },
+    /// `node` was expanded, meaning that its successors were retrieved and registered.
+    Expanded { node: N, parent: Option<N> },
+    /// `node` was found to be a goal.
+    GoalFound { node: N, cost: C },
+    /// The visited map outgrew the memory limit of the context, so `dropped` nodes were forgotten,
+    /// leaving `visited` nodes.
+    Pruned { dropped: usize, visited: usize },
+}
+
+/// Receives [`Event`]s describing the work a search does, which is useful both for collecting
+/// [`Stats`] and for debugging or visualizing searches.
+///
+/// Since observers are resolved statically, `()` (which ignores every event) compiles down to
+/// nothing at all. That's what a [`SearchContext`] uses unless told otherwise.
+pub trait Observer<N, C> {
+    fn observe(&mut self, event: Event<N, C>);
+}
+
+impl<N, C> Observer<N, C> for () {
+    #[inline(always)]
+    fn observe(&mut self, _: Event<N, C>) {}
+}
+
+impl<N, C> Observer<N, C> for Stats {
+    fn observe(&mut self, event: Event<N, C>) {
+        match event {
+            Event::Started { .. } => *self = Stats::default(),
+            Event::Pushed { pending, visited, .. } => {
+                self.pushed += 1;
+                self.peak_pending = self.peak_pending.max(pending);
+                self.peak_visited = self.peak_visited.max(visited);
+            }
+            Event::Stale { .. } => self.stale += 1,
+            Event::MoveChecked { .. } => self.valid_move_checks += 1,
+            Event::JumpOffered { .. } => self.jumps += 1,
+            Event::FallbackPushed { .. } => self.fallbacks += 1,
+            Event::Expanded { .. } => self.expanded += 1,
+            Event::Pruned { dropped, .. } => self.pruned += dropped,
+            Event::Popped { .. }
+            | Event::JumpAccepted { .. }
+            | Event::JumpRejected { .. }
+            | Event::GoalFound { .. } => {}
+        }
+    }
+}
+
+/// Keeps a log of every event, cleared at the start of every search.
+impl<N, C> Observer<N, C> for Vec<Event<N, C>> {
+    fn observe(&mut self, event: Event<N, C>) {
+        if let Event::Started { .. } = event {
+            self.clear();
+        }
+
+        self.push(event);
+    }
+}
+
+/// Passes every event on to both observers.
+impl<N: Copy, C: Copy, A: Observer<N, C>, B: Observer<N, C>> Observer<N, C> for (A, B) {
+    #[inline(always)]
+    fn observe(&mut self, event: Event<N, C>) {
+        self.0.observe(event);
+        self.1.observe(event);
+    }
+}
+
+/// How a search picks between pending nodes with the same estimated cost.
+///
+/// Open maps tend to have a lot of nodes that are estimated to be equally good, and which of them
+/// is expanded first can make a big difference in how many nodes are expanded before the goal is
+/// reached. The cost of the path found is the same either way, but which of several equally good
+/// paths is found may change.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum TieBreak {
+    /// Prefers the node that was the most expensive to reach, which is the same as preferring the
+    /// node with the lowest heuristic. This heads for the goal rather than widening the search, and
+    /// is usually the best choice.
+    #[default]
+    HigherCost,
+    /// Prefers the node that was the cheapest to reach, which is the same as preferring the node
+    /// with the highest heuristic.
+    LowerCost,
+    /// Prefers the node that was pushed first.
+    Fifo,
+    /// Prefers the node that was pushed last, which behaves a bit like a depth-first search.
+    Lifo,
+    /// Prefers the node that [`SearchProblem::tie_break`] ranks highest, such as the node that's
+    /// furthest along in time.
+    Custom,
+}
+
+// Jumps that skip several ancestors pick where to jump from by walking up the parents of the node
+// being expanded, and fall back by walking them again once rejected. If an expanded node could
+// still be reached a cheaper way later on, its descendants would end up with ancestors they were
+// never checked against. Deeper jumps therefore close every node they expand, so that the way to
+// it is final and any other ways to it found later on are ignored. Jumps that only skip a single
+// ancestor don't walk any further than the parent they were offered from, so nodes stay open.
+type Visited<N, C> = FxIndexMap<N, (usize, C, bool, bool)>;
+
+/// The buffers a search works in.
+///
+/// Every search needs a heap of pending nodes and a map of visited nodes. The free functions in
+/// this module allocate both from scratch for every query, which adds up when running many queries
+/// per second. Holding on to a `SearchContext` and running the searches through it instead keeps
+/// the buffers (and their capacity) around between runs. The buffers are cleared at the start of
+/// every search, so a context can be reused for any number of unrelated queries.
+///
+/// A context also holds an [`Observer`], which is `()` unless created with
+/// [`SearchContext::with_observer`]. Passing [`Stats`] there collects statistics for every search
+/// run through the context, which can be read back through [`SearchContext::observer`].
+///
+/// Normally the visited map only ever grows during a search, which can take a lot of memory on hard
+/// queries. [`SearchContext::with_memory_limit`] caps it instead. How nodes that are estimated to be
+/// equally good are ordered can be picked with [`SearchContext::with_tie_break`].
+pub struct SearchContext<N, C, O = ()> {
+    // All the nodes we've seen but haven't yet validated or expanded.
+    pending: BinaryHeap<Pending<C, N>>,
+    // All potentially referenced nodes, with the index of their parent, the cost to get to them,
+    // whether they were reached by jumping, and whether they've been expanded and closed.
+    visited: Visited<N, C>,
+    // Told about everything we do.
+    observer: O,
+    // The most nodes the visited map may hold before it's pruned.
+    memory_limit: Option<usize>,
+    // How pending nodes with the same estimated cost are ordered.
+    tie_break: TieBreak,
+    // How many ancestors a jump may skip over.
+    jump_depth: usize,
+    // The number of nodes pushed so far in the current search, which orders them for FIFO and LIFO
+    // tie-breaking.
+    pushed: usize,
+}
+
+impl<N, C, O: Default> Default for SearchContext<N, C, O> {
+    fn default() -> Self {
+        Self::with_observer(O::default())
+    }
+}
+
+impl<N, C> SearchContext<N, C> {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a context with room for `capacity` nodes before it has to reallocate.
+    #[must_use]
+    pub fn with_capacity(capacity: usize) -> Self {
+        SearchContext {
+            pending: BinaryHeap::with_capacity(capacity),
+            visited: FxIndexMap::with_capacity_and_hasher(capacity, Default::default()),
+            observer: (),
+            memory_limit: None,
+            tie_break: TieBreak::default(),
+            jump_depth: 1,
+            pushed: 0,
+        }
+    }
+}
+
+impl<N, C, O> SearchContext<N, C, O> {
+    #[must_use]
+    pub fn with_observer(observer: O) -> Self {
+        SearchContext {
+            pending: BinaryHeap::new(),
+            visited: FxIndexMap::default(),
+            observer,
+            memory_limit: None,
+            tie_break: TieBreak::default(),
+            jump_depth: 1,
+            pushed: 0,
+        }
+    }
+
+    /// Keeps the visited map from holding more than about `limit` nodes.
+    ///
+    /// Whenever the visited map outgrows the limit, the least promising pending nodes are dropped,
+    /// along with every visited node that none of the remaining ones lead back to, until about half
+    /// of the limit is left. This turns the search into a beam search: paths it finds are still
+    /// valid, but they may not be the cheapest ones, and a path may not be found at all if every
+    /// way to it was dropped. Since forgotten nodes can be reached and expanded again, a search
+    /// with a memory limit should also have a [`Budget`] to bound how long it can take.
+    ///
+    /// A search can go slightly over the limit, by up to the number of successors of a node, and
+    /// by however many nodes the best pending node needs to lead back to the start.
+    #[must_use]
+    pub fn with_memory_limit(mut self, limit: usize) -> Self {
+        self.memory_limit = Some(limit);
+        self
+    }
+
+    /// Picks how pending nodes with the same estimated cost are ordered, which defaults to
+    /// [`TieBreak::HigherCost`].
+    #[must_use]
+    pub fn with_tie_break(mut self, tie_break: TieBreak) -> Self {
+        self.tie_break = tie_break;
+        self
+    }
+
+    /// Sets how many ancestors a jump may skip over, which defaults to `1`.
+    ///
+    /// By default a jump only ever skips the node being expanded, going straight from its parent
+    /// to a successor. Skipping more than one ancestor lets a single jump straighten out several
+    /// corners at once, such as the ones a path was forced into right before it came out from
+    /// behind something. The jump is made from the furthest ancestor `jump_check` allows, by asking
+    /// it about every ancestor in turn with the node it returned for the one before. If the jump
+    /// turns out to be invalid, the jump from the ancestor before it is tried instead, all the way
+    /// down to the normal move.
+    ///
+    /// Deeper jumps take more calls to `is_valid_move` whenever they're rejected. Since a node
+    /// keeps the first way to it that's expanded, they also don't always lead to cheaper paths,
+    /// so it's worth measuring whether they help on a given map. A depth of `0` turns jumps off
+    /// entirely.
+    #[must_use]
+    pub fn with_jump_depth(mut self, depth: usize) -> Self {
+        self.jump_depth = depth;
+        self
+    }
+
+    /// The observer, which holds the statistics of the latest search if it's [`Stats`].
+    #[must_use]
+    pub fn observer(&self) -> &O {
+        &self.observer
+    }
+
+    #[must_use]
+    pub fn observer_mut(&mut self) -> &mut O {
+        &mut self.observer
+    }
+
+    /// Clears the buffers while keeping their allocated capacity.
+    pub fn clear(&mut self) {
+        self.pending.clear();
+        self.visited.clear();
+        self.pushed = 0;
+    }
+}
+
+impl<N, C, O> SearchContext<N, C, O>
+where
+    N: Eq + Hash + Copy,
+    C: Zero + Ord + Copy,
+    O: Observer<N, C>,
+{
+    /// Searches for a path to a goal of `problem`, stopping once `budget` is exceeded.
+    pub fn search<P>(&mut self, problem: &mut P, start: N, budget: &Budget) -> Result<Path<N, C>, FindError>
+    where
+        P: SearchProblem<Node = N, Cost = C>,
+    {
+        self.seed(start);
+        find_inner(self, problem, budget, false)
+    }
+
+    /// Like [`SearchContext::search`], but first tries moving straight from `start` to each of
+    /// `goals`, avoiding the search entirely if nothing is in the way.
+    ///
+    /// The goals are tried from the cheapest to move to, and the first valid move is returned as a
+    /// [`Route::Direct`] path without expanding anything. Goals that [`SearchProblem::success`]
+    /// doesn't accept are skipped, so `goals` can be a handful of candidates around the actual
+    /// target, such as the cells a pawn could stand on next to it. If none of them can be moved to
+    /// directly, this falls back to a full search and returns its path as a [`Route::Searched`].
+    pub fn search_direct<P>(
+        &mut self,
+        problem: &mut P,
+        start: N,
+        goals: impl IntoIterator<Item = N>,
+        budget: &Budget,
+    ) -> Result<Route<N, C>, FindError>
+    where
+        P: SearchProblem<Node = N, Cost = C>,
+    {
+        self.seed(start);
+
+        let mut goals = goals
+            .into_iter()
+            .filter_map(|goal| {
+                let candidate = goal != start && problem.success(&goal);
+                candidate.then(|| (problem.movement_cost(&start, &goal), goal))
+            })
+            .collect::<Vec<_>>();
+        goals.sort_by_key(|&(cost, _)| cost);
+
+        for (cost, goal) in goals {
+            let valid = problem.is_valid_move(&start, &goal);
+            self.observer.observe(Event::MoveChecked { from: start, to: goal, valid });
+
+            if valid {
+                let (index, _) = self.visited.insert_full(goal, (0, cost, false, false));
+                self.observer.observe(Event::GoalFound { node: goal, cost });
+                return Ok(Route::Direct(build_path(&self.visited, index, false)));
+            }
+        }
+
+        find_inner(self, problem, budget, false).map(Route::Searched)
+    }
+
+    /// Like [`SearchContext::search`], but starts out from the valid moves in `initialize`.
+    pub fn search_with_init<P>(
+        &mut self,
+        problem: &mut P,
+        start: N,
+        initialize: impl IntoIterator<Item = (N, C)>,
+        budget: &Budget,
+    ) -> Result<Path<N, C>, FindError>
+    where
+        P: SearchProblem<Node = N, Cost = C>,
+    {
+        self.seed_with_init(problem, start, initialize)?;
+        find_inner(self, problem, budget, false)
+    }
+
+    /// Like [`SearchContext::search`], but returns a best-effort path if no goal could be reached,
+    /// either because the search was exhausted or because `budget` was exceeded.
+    ///
+    /// The partial path leads to the reached node with the lowest [`SearchProblem::progress`].
+    /// Since the start node is always reached, a path is always returned.
+    pub fn search_partial<P>(&mut self, problem: &mut P, start: N, budget: &Budget) -> Path<N, C>
+    where
+        P: SearchProblem<Node = N, Cost = C>,
+    {
+        self.seed(start);
+
+        // Partial searches only ever fail if we didn't tell them to track progress.
+        find_inner(self, problem, budget, true).unwrap_or_else(|_| unreachable!())
+    }
+
+    /// Like [`SearchContext::search_partial`], but starts out from the valid moves in `initialize`.
+    pub fn search_with_init_partial<P>(
+        &mut self,
+        problem: &mut P,
+        start: N,
+        initialize: impl IntoIterator<Item = (N, C)>,
+        budget: &Budget,
+    ) -> Path<N, C>
+    where
+        P: SearchProblem<Node = N, Cost = C>,
+    {
+        // Without any valid initial moves the partial path is just the start node, which is as far
+        // as we can get anyway.
+        let _ = self.seed_with_init(problem, start, initialize);
+
+        // Partial searches only ever fail if we didn't tell them to track progress.
+        find_inner(self, problem, budget, true).unwrap_or_else(|_| unreachable!())
+    }
+
+    /// This is solely a convenience function.
+    #[allow(clippy::too_many_arguments)]
+    pub fn find_with_optional_init<IterSuccessors>(
+        &mut self,
+        start: N,
+        initialize: Option<impl IntoIterator<Item = (N, C)>>,
+        successors: impl FnMut(&N) -> IterSuccessors,
+        is_valid_move: impl FnMut(&N, &N) -> bool,
+        movement_cost: impl FnMut(&N, &N) -> C,
+        heuristic: impl FnMut(&N) -> C,
+        success: impl FnMut(&N) -> bool,
+        jump_check: impl FnMut(&N, &N, &N) -> Option<N>,
+    ) -> Result<Path<N, C>, FindError>
+    where
+        IterSuccessors: IntoIterator<Item = (N, C)>,
+    {
+        // TODO: Split the init "trampolines" to separate functions so that this can easier inline?
+
+        if let Some(initialize) = initialize {
+            self.find_with_init(
+                start,
+                initialize,
+                successors,
+                is_valid_move,
+                movement_cost,
+                heuristic,
+                success,
+                jump_check,
+            )
+        } else {
+            self.find(start, successors, is_valid_move, movement_cost, heuristic, success, jump_check)
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn find_with_init<IterSuccessors>(
+        &mut self,
+        start: N,
+        initialize: impl IntoIterator<Item = (N, C)>,
+        successors: impl FnMut(&N) -> IterSuccessors,
+        is_valid_move: impl FnMut(&N, &N) -> bool,
+        movement_cost: impl FnMut(&N, &N) -> C,
+        heuristic: impl FnMut(&N) -> C,
+        success: impl FnMut(&N) -> bool,
+        jump_check: impl FnMut(&N, &N, &N) -> Option<N>,
+    ) -> Result<Path<N, C>, FindError>
+    where
+        IterSuccessors: IntoIterator<Item = (N, C)>,
+    {
+        self.find_with_init_bounded(
+            start,
+            initialize,
+            &Budget::default(),
+            successors,
+            is_valid_move,
+            movement_cost,
+            heuristic,
+            success,
+            jump_check,
+        )
+    }
+
+    /// Like [`SearchContext::find_with_init`], but stops once `budget` is exceeded.
+    #[allow(clippy::too_many_arguments)]
+    pub fn find_with_init_bounded<IterSuccessors>(
+        &mut self,
+        start: N,
+        initialize: impl IntoIterator<Item = (N, C)>,
+        budget: &Budget,
+        successors: impl FnMut(&N) -> IterSuccessors,
+        is_valid_move: impl FnMut(&N, &N) -> bool,
+        movement_cost: impl FnMut(&N, &N) -> C,
+        heuristic: impl FnMut(&N) -> C,
+        success: impl FnMut(&N) -> bool,
+        jump_check: impl FnMut(&N, &N, &N) -> Option<N>,
+    ) -> Result<Path<N, C>, FindError>
+    where
+        IterSuccessors: IntoIterator<Item = (N, C)>,
+    {
+        let mut problem = FnProblem::new(successors, is_valid_move, movement_cost, heuristic, success, jump_check);
+        self.search_with_init(&mut problem, start, initialize, budget)
+    }
+
+    /// Like [`SearchContext::find_with_init_bounded`], but returns a best-effort path if no goal
+    /// could be reached, either because the search was exhausted or because `budget` was exceeded.
+    ///
+    /// The partial path leads to the reached node with the lowest `progress` value. Since the
+    /// start node is always reached, a path is always returned.
+    #[allow(clippy::too_many_arguments)]
+    pub fn find_with_init_partial<IterSuccessors>(
+        &mut self,
+        start: N,
+        initialize: impl IntoIterator<Item = (N, C)>,
+        budget: &Budget,
+        successors: impl FnMut(&N) -> IterSuccessors,
+        is_valid_move: impl FnMut(&N, &N) -> bool,
+        movement_cost: impl FnMut(&N, &N) -> C,
+        heuristic: impl FnMut(&N) -> C,
+        success: impl FnMut(&N) -> bool,
+        jump_check: impl FnMut(&N, &N, &N) -> Option<N>,
+        progress: impl FnMut(&N) -> C,
+    ) -> Path<N, C>
+    where
+        IterSuccessors: IntoIterator<Item = (N, C)>,
+    {
+        let problem = FnProblem::new(successors, is_valid_move, movement_cost, heuristic, success, jump_check);
+        self.search_with_init_partial(&mut WithProgress { problem, progress }, start, initialize, budget)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn find<IterSuccessors>(
+        &mut self,
+        start: N,
+        successors: impl FnMut(&N) -> IterSuccessors,
+        is_valid_move: impl FnMut(&N, &N) -> bool,
+        movement_cost: impl FnMut(&N, &N) -> C,
+        heuristic: impl FnMut(&N) -> C,
+        success: impl FnMut(&N) -> bool,
+        jump_check: impl FnMut(&N, &N, &N) -> Option<N>,
+    ) -> Result<Path<N, C>, FindError>
+    where
+        IterSuccessors: IntoIterator<Item = (N, C)>,
+    {
+        let budget = Budget::default();
+        self.find_bounded(start, &budget, successors, is_valid_move, movement_cost, heuristic, success, jump_check)
+    }
+
+    /// Like [`SearchContext::find`], but stops once `budget` is exceeded.
+    #[allow(clippy::too_many_arguments)]
+    pub fn find_bounded<IterSuccessors>(
+        &mut self,
+        start: N,
+        budget: &Budget,
+        successors: impl FnMut(&N) -> IterSuccessors,
+        is_valid_move: impl FnMut(&N, &N) -> bool,
+        movement_cost: impl FnMut(&N, &N) -> C,
+        heuristic: impl FnMut(&N) -> C,
+        success: impl FnMut(&N) -> bool,
+        jump_check: impl FnMut(&N, &N, &N) -> Option<N>,
+    ) -> Result<Path<N, C>, FindError>
+    where
+        IterSuccessors: IntoIterator<Item = (N, C)>,
+    {
+        let mut problem = FnProblem::new(successors, is_valid_move, movement_cost, heuristic, success, jump_check);
+        self.search(&mut problem, start, budget)
+    }
+
+    /// Like [`SearchContext::find_bounded`], but returns a best-effort path if no goal could be
+    /// reached, either because the search was exhausted or because `budget` was exceeded.
+    ///
+    /// The partial path leads to the reached node with the lowest `progress` value. Since the
+    /// start node is always reached, a path is always returned.
+    #[allow(clippy::too_many_arguments)]
+    pub fn find_partial<IterSuccessors>(
+        &mut self,
+        start: N,
+        budget: &Budget,
+        successors: impl FnMut(&N) -> IterSuccessors,
+        is_valid_move: impl FnMut(&N, &N) -> bool,
+        movement_cost: impl FnMut(&N, &N) -> C,
+        heuristic: impl FnMut(&N) -> C,
+        success: impl FnMut(&N) -> bool,
+        jump_check: impl FnMut(&N, &N, &N) -> Option<N>,
+        progress: impl FnMut(&N) -> C,
+    ) -> Path<N, C>
+    where
+        IterSuccessors: IntoIterator<Item = (N, C)>,
+    {
+        let problem = FnProblem::new(successors, is_valid_move, movement_cost, heuristic, success, jump_check);
+        self.search_partial(&mut WithProgress { problem, progress }, start, budget)
+    }
+
+    /// Resets the context and registers `start` as the first node to expand.
+    pub(crate) fn seed(&mut self, start: N) {
+        self.clear();
+        self.observer.observe(Event::Started { start });
+
+        // Add the start node to the visited map, and a reference to it in the pending heap.
+        self.visited.insert(start, (usize::MAX, Zero::zero(), false, false));
+        let tie = Tie::Greater(Zero::zero());
+        self.pending.push(Pending { estimated_cost: Zero::zero(), cost: Zero::zero(), tie, index: 0, fallback: None });
+
+        let (pending, visited) = (self.pending.len(), self.visited.len());
+        let cost = Zero::zero();
+        self.observer.observe(Event::Pushed { node: start, parent: None, cost, pending, visited });
+    }
+
+    /// The index of `node` in the visited map, if the latest search reached it.
+    pub(crate) fn index_of(&self, node: &N) -> Option<usize> {
+        self.visited.get_index_of(node)
+    }
+
+    /// Resets the context and registers the valid moves in `initialize` as the first nodes to
+    /// expand, with `start` as their parent.
+    fn seed_with_init<P>(
+        &mut self,
+        problem: &mut P,
+        start: N,
+        initialize: impl IntoIterator<Item = (N, C)>,
+    ) -> Result<(), FindError>
+    where
+        P: SearchProblem<Node = N, Cost = C>,
+    {
+        self.clear();
+        self.observer.observe(Event::Started { start });
+
+        // Insert the root position as our starting position.
+        let (n_parent_idx, _) = self.visited.insert_full(start, (usize::MAX, Zero::zero(), false, false));
+
+        let (mut moves, mut valid_moves) = (0, 0);
+
+        // Add the start nodes to the visited map, and references to them in the pending heap.
+        for (node, cost) in initialize {
+            moves += 1;
+
+            // If the node can be moved to, register it as a pending node with the start node as its parent.
+            let valid = problem.is_valid_move(&start, &node);
+            self.observer.observe(Event::MoveChecked { from: start, to: node, valid });
+
+            if valid {
+                valid_moves += 1;
+                let SearchContext { pending, visited, observer, tie_break, pushed, .. } = self;
+                let order = (*tie_break, pushed);
+                add_pending(visited, pending, observer, order, problem, None, n_parent_idx, cost, node, None);
+            }
+        }
+
+        match (moves, valid_moves) {
+            (0, _) => Err(FindError::InvalidInput),
+            (_, 0) => Err(FindError::NoValidInitialMoves),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// What a [`Search`] is up to after a call to [`Search::step`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Step<N, C> {
+    /// The search isn't done yet, and needs more steps.
+    Pending,
+    /// A goal was reached.
+    Found(Path<N, C>),
+    /// Every reachable node was expanded without reaching a goal.
+    Exhausted,
+}
+
+/// A search that's driven one step at a time, which allows spreading an expensive search over
+/// several frames without involving any threads.
+///
+/// The search keeps all of its state between steps, so taking many small steps finds a path just as
+/// good as a single call to [`find`] would. Only ties between equally good paths may be broken
+/// differently.
+pub struct Search<P: SearchProblem, O = ()> {
+    problem: P,
+    ctx: SearchContext<P::Node, P::Cost, O>,
+    // The outcome once the search is done, which every following step returns as well.
+    done: Option<Step<P::Node, P::Cost>>,
+}
+
+impl<N, C, S, V, M, H, G, J, IterSuccessors> Search<FnProblem<N, C, S, V, M, H, G, J>>
+where
+    N: Eq + Hash + Copy,
+    C: Zero + Ord + Copy,
+    IterSuccessors: IntoIterator<Item = (N, C)>,
+    S: FnMut(&N) -> IterSuccessors,
+    V: FnMut(&N, &N) -> bool,
+    M: FnMut(&N, &N) -> C,
+    H: FnMut(&N) -> C,
+    G: FnMut(&N) -> bool,
+    J: FnMut(&N, &N, &N) -> Option<N>,
+{
+    /// Takes the same arguments as [`find`], but doesn't do any searching until stepped.
+    #[must_use]
+    pub fn new(
+        start: N,
+        successors: S,
+        is_valid_move: V,
+        movement_cost: M,
+        heuristic: H,
+        success: G,
+        jump_check: J,
+    ) -> Self {
+        let problem = FnProblem::new(successors, is_valid_move, movement_cost, heuristic, success, jump_check);
+        Search::from_problem(problem, start)
+    }
+}
+
+impl<P: SearchProblem> Search<P> {
+    #[must_use]
+    pub fn from_problem(problem: P, start: P::Node) -> Self {
+        Search::with_context(problem, start, SearchContext::new())
+    }
+}
+
+impl<P: SearchProblem, O: Observer<P::Node, P::Cost>> Search<P, O> {
+    /// Like [`Search::from_problem`], but searches using the buffers and observer of `ctx`.
+    #[must_use]
+    pub fn with_context(problem: P, start: P::Node, mut ctx: SearchContext<P::Node, P::Cost, O>) -> Self {
+        ctx.seed(start);
+        Search { problem, ctx, done: None }
+    }
+
+    /// Expands up to `n` more nodes.
+    pub fn step(&mut self, n: usize) -> Step<P::Node, P::Cost> {
+        self.step_within(&Budget { max_expansions: Some(n), ..Budget::default() })
+    }
+
+    /// Keeps expanding nodes until `budget` is exceeded, such as when a frame's time is up.
+    pub fn step_within(&mut self, budget: &Budget) -> Step<P::Node, P::Cost> {
+        if let Some(done) = &self.done {
+            return done.clone();
+        }
+
+        let step = match find_inner(&mut self.ctx, &mut self.problem, budget, false) {
+            Ok(path) => Step::Found(path),
+            Err(FindError::BudgetExceeded(_)) => return Step::Pending,
+            Err(_) => Step::Exhausted,
+        };
+
+        self.done = Some(step.clone());
+        step
+    }
+
+    /// Gives back the context, so that its buffers can be reused for other searches.
+    #[must_use]
+    pub fn into_context(self) -> SearchContext<P::Node, P::Cost, O> {
+        self.ctx
+    }
+}
 
 /// This is solely a convenience function.
+#[allow(clippy::too_many_arguments)]
 pub fn find_with_optional_init<N, C, IterSuccessors>(
     start: N,
     initialize: Option<impl IntoIterator<Item = (N, C)>>,
@@ -61,108 +1101,247 @@ pub fn find_with_optional_init<N, C, IterSuccessors>(
     heuristic: impl FnMut(&N) -> C,
     success: impl FnMut(&N) -> bool,
     jump_check: impl FnMut(&N, &N, &N) -> Option<N>,
-) -> Option<(Vec<N>, C)>
+) -> Result<Path<N, C>, FindError>
 where
     N: Eq + Hash + Copy,
     C: Zero + Ord + Copy,
     IterSuccessors: IntoIterator<Item = (N, C)>,
 {
-    // TODO: Split the init "trampolines" to separate functions so that this can easier inline?
-
-    if let Some(initialize) = initialize {
-        find_with_init(start, initialize, successors, is_valid_move, movement_cost, heuristic, success, jump_check)
-    } else {
-        find(start, successors, is_valid_move, movement_cost, heuristic, success, jump_check)
-    }
+    SearchContext::new().find_with_optional_init(
+        start,
+        initialize,
+        successors,
+        is_valid_move,
+        movement_cost,
+        heuristic,
+        success,
+        jump_check,
+    )
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn find_with_init<N, C, IterSuccessors>(
     start: N,
     initialize: impl IntoIterator<Item = (N, C)>,
     successors: impl FnMut(&N) -> IterSuccessors,
-    mut is_valid_move: impl FnMut(&N, &N) -> bool,
+    is_valid_move: impl FnMut(&N, &N) -> bool,
     movement_cost: impl FnMut(&N, &N) -> C,
-    mut heuristic: impl FnMut(&N) -> C,
+    heuristic: impl FnMut(&N) -> C,
     success: impl FnMut(&N) -> bool,
     jump_check: impl FnMut(&N, &N, &N) -> Option<N>,
-) -> Option<(Vec<N>, C)>
+) -> Result<Path<N, C>, FindError>
 where
     N: Eq + Hash + Copy,
     C: Zero + Ord + Copy,
     IterSuccessors: IntoIterator<Item = (N, C)>,
 {
-    // Set up the two main collections we'll be using.
-    let mut pending = BinaryHeap::new();
-    let mut visited = FxIndexMap::default();
-
-    // Insert the root position as our starting position.
-    let (n_parent_idx, _) = visited.insert_full(start, (usize::MAX, Zero::zero()));
-
-    // Add the start nodes to the visited map, and references to them in the pending heap.
-    for (node, cost) in initialize {
-        // If the node can be moved to, register it as a pending node with the start node as its parent.
-        if is_valid_move(&start, &node) {
-            add_pending(&mut visited, &mut pending, &mut heuristic, n_parent_idx, cost, node, None);
-        }
-    }
+    SearchContext::new().find_with_init(
+        start,
+        initialize,
+        successors,
+        is_valid_move,
+        movement_cost,
+        heuristic,
+        success,
+        jump_check,
+    )
+}
+
+/// See [`SearchContext::find_with_init_bounded`].
+#[allow(clippy::too_many_arguments)]
+pub fn find_with_init_bounded<N, C, IterSuccessors>(
+    start: N,
+    initialize: impl IntoIterator<Item = (N, C)>,
+    budget: &Budget,
+    successors: impl FnMut(&N) -> IterSuccessors,
+    is_valid_move: impl FnMut(&N, &N) -> bool,
+    movement_cost: impl FnMut(&N, &N) -> C,
+    heuristic: impl FnMut(&N) -> C,
+    success: impl FnMut(&N) -> bool,
+    jump_check: impl FnMut(&N, &N, &N) -> Option<N>,
+) -> Result<Path<N, C>, FindError>
+where
+    N: Eq + Hash + Copy,
+    C: Zero + Ord + Copy,
+    IterSuccessors: IntoIterator<Item = (N, C)>,
+{
+    SearchContext::new().find_with_init_bounded(
+        start,
+        initialize,
+        budget,
+        successors,
+        is_valid_move,
+        movement_cost,
+        heuristic,
+        success,
+        jump_check,
+    )
+}
+
+/// See [`SearchContext::find_with_init_partial`].
+#[allow(clippy::too_many_arguments)]
+pub fn find_with_init_partial<N, C, IterSuccessors>(
+    start: N,
+    initialize: impl IntoIterator<Item = (N, C)>,
+    budget: &Budget,
+    successors: impl FnMut(&N) -> IterSuccessors,
+    is_valid_move: impl FnMut(&N, &N) -> bool,
+    movement_cost: impl FnMut(&N, &N) -> C,
+    heuristic: impl FnMut(&N) -> C,
+    success: impl FnMut(&N) -> bool,
+    jump_check: impl FnMut(&N, &N, &N) -> Option<N>,
+    progress: impl FnMut(&N) -> C,
+) -> Path<N, C>
+where
+    N: Eq + Hash + Copy,
+    C: Zero + Ord + Copy,
+    IterSuccessors: IntoIterator<Item = (N, C)>,
+{
+    SearchContext::new().find_with_init_partial(
+        start,
+        initialize,
+        budget,
+        successors,
+        is_valid_move,
+        movement_cost,
+        heuristic,
+        success,
+        jump_check,
+        progress,
+    )
+}
 
-    find_inner(pending, visited, successors, is_valid_move, movement_cost, heuristic, success, jump_check)
+pub fn find<N, C, IterSuccessors>(
+    start: N,
+    successors: impl FnMut(&N) -> IterSuccessors,
+    is_valid_move: impl FnMut(&N, &N) -> bool,
+    movement_cost: impl FnMut(&N, &N) -> C,
+    heuristic: impl FnMut(&N) -> C,
+    success: impl FnMut(&N) -> bool,
+    jump_check: impl FnMut(&N, &N, &N) -> Option<N>,
+) -> Result<Path<N, C>, FindError>
+where
+    N: Eq + Hash + Copy,
+    C: Zero + Ord + Copy,
+    IterSuccessors: IntoIterator<Item = (N, C)>,
+{
+    SearchContext::new().find(start, successors, is_valid_move, movement_cost, heuristic, success, jump_check)
+}
+
+/// See [`SearchContext::find_bounded`].
+#[allow(clippy::too_many_arguments)]
+pub fn find_bounded<N, C, IterSuccessors>(
+    start: N,
+    budget: &Budget,
+    successors: impl FnMut(&N) -> IterSuccessors,
+    is_valid_move: impl FnMut(&N, &N) -> bool,
+    movement_cost: impl FnMut(&N, &N) -> C,
+    heuristic: impl FnMut(&N) -> C,
+    success: impl FnMut(&N) -> bool,
+    jump_check: impl FnMut(&N, &N, &N) -> Option<N>,
+) -> Result<Path<N, C>, FindError>
+where
+    N: Eq + Hash + Copy,
+    C: Zero + Ord + Copy,
+    IterSuccessors: IntoIterator<Item = (N, C)>,
+{
+    SearchContext::new().find_bounded(
+        start,
+        budget,
+        successors,
+        is_valid_move,
+        movement_cost,
+        heuristic,
+        success,
+        jump_check,
+    )
 }
 
-pub fn find<N, C, IterSuccessors>(
+/// See [`SearchContext::find_partial`].
+#[allow(clippy::too_many_arguments)]
+pub fn find_partial<N, C, IterSuccessors>(
     start: N,
+    budget: &Budget,
     successors: impl FnMut(&N) -> IterSuccessors,
     is_valid_move: impl FnMut(&N, &N) -> bool,
     movement_cost: impl FnMut(&N, &N) -> C,
     heuristic: impl FnMut(&N) -> C,
     success: impl FnMut(&N) -> bool,
     jump_check: impl FnMut(&N, &N, &N) -> Option<N>,
-) -> Option<(Vec<N>, C)>
+    progress: impl FnMut(&N) -> C,
+) -> Path<N, C>
 where
     N: Eq + Hash + Copy,
     C: Zero + Ord + Copy,
     IterSuccessors: IntoIterator<Item = (N, C)>,
 {
-    // Set up the two main collections we'll be using.
-    let mut visited = FxIndexMap::default();
-    let mut pending = BinaryHeap::new();
+    SearchContext::new().find_partial(
+        start,
+        budget,
+        successors,
+        is_valid_move,
+        movement_cost,
+        heuristic,
+        success,
+        jump_check,
+        progress,
+    )
+}
 
-    // Add the start node to the visited map, and a reference to it in the pending heap.
-    visited.insert(start, (usize::MAX, Zero::zero()));
-    pending.push(Pending { estimated_cost: Zero::zero(), cost: Zero::zero(), index: 0, fallback: None });
+pub(crate) fn find_inner<P: SearchProblem, O: Observer<P::Node, P::Cost>>(
+    ctx: &mut SearchContext<P::Node, P::Cost, O>,
+    problem: &mut P,
+    budget: &Budget,
 
-    find_inner(pending, visited, successors, is_valid_move, movement_cost, heuristic, success, jump_check)
-}
+    // If set, we keep track of the best node we've reached so that we can fall back to a path
+    // to it if we never reach a goal.
+    partial: bool,
+) -> Result<Path<P::Node, P::Cost>, FindError> {
+    let SearchContext { pending, visited, observer, memory_limit, tie_break, jump_depth, pushed } = ctx;
+    let (tie_break, jump_depth) = (*tie_break, *jump_depth);
 
-fn find_inner<N, C, IterSuccessors>(
-    mut pending: BinaryHeap<Pending<C, N>>, // All the nodes we've seen but haven't yet validated or expanded.
-    mut visited: FxIndexMap<N, (usize, C)>, // All potentially referenced nodes.
+    // The number of nodes we've expanded so far, which is what the budget is measured in.
+    let mut expansions = 0;
+
+    // The progress value and index of the best node we've reached so far. The root node is always
+    // at index 0 and is always reached, so that's where a partial path starts out ending.
+    let mut best: Option<(P::Cost, usize)> = None;
+
+    // Builds the best-effort path if we're tracking progress, or reports why we stopped otherwise.
+    let give_up = |visited: &Visited<P::Node, P::Cost>, best: Option<(P::Cost, usize)>, error| {
+        if !partial {
+            return Err(error);
+        }
+
+        Ok(build_path(visited, best.map_or(0, |(_, index)| index), true))
+    };
 
-    mut successors: impl FnMut(&N) -> IterSuccessors,
-    mut is_valid_move: impl FnMut(&N, &N) -> bool,
-    mut movement_cost: impl FnMut(&N, &N) -> C,
-    mut heuristic: impl FnMut(&N) -> C,
-    mut success: impl FnMut(&N) -> bool,
-    mut jump_check: impl FnMut(&N, &N, &N) -> Option<N>,
-) -> Option<(Vec<N>, C)>
-where
-    N: Eq + Hash + Copy,
-    C: Zero + Ord + Copy,
-    IterSuccessors: IntoIterator<Item = (N, C)>,
-{
     // pX = parent X - p0 = current node, p1 = parent of p0, p2 = parent of p1, etc.
-    while let Some(Pending { cost, index: p0_index, fallback, .. }) = pending.pop() {
+    while let Some(Pending { estimated_cost, cost, tie, index: p0_index, fallback }) = pending.pop() {
         // This isn't strictly required to be unchecked, but it helps quite a bit with performance.
-        // We're never going to be holding an invalid index since we never remove elements from the visited list.
-        let (p0_node, &(p1_index, p0_cost)) = unsafe { visited.get_index(p0_index).unwrap_unchecked() };
+        // We're never going to be holding an invalid index since elements are only ever removed from
+        // the visited list by `prune`, which updates the indices we hold to match.
+        let (p0_node, &(p1_index, p0_cost, _, closed)) = unsafe { visited.get_index(p0_index).unwrap_unchecked() };
+        observer.observe(Event::Popped { node: *p0_node, cost });
 
         // We may have inserted a node several time into the binary heap if we found a better way
         // to access it since. If that's the case and the existing node is better than the current
-        // one, we're not interested in evaluating this one.
-        if p0_cost < cost {
+        // one, we're not interested in evaluating this one. The existing node can also be worse,
+        // if it replaced a rejected jump, in which case this one's parent isn't known anymore. With
+        // deeper jumps, a node that's already been expanded is closed as well.
+        if p0_cost != cost || closed {
+            observer.observe(Event::Stale { node: *p0_node });
             continue;
         }
 
+        // Checking whether we're still allowed to keep going before validating the move means that
+        // the node can be put back untouched, so that a step-wise search picks up exactly where it
+        // left off without validating anything twice.
+        if let Some(limit) = budget.exceeded(expansions, visited.len()) {
+            pending.push(Pending { estimated_cost, cost, tie, index: p0_index, fallback });
+            return give_up(visited, best, FindError::BudgetExceeded(limit));
+        }
+
         // This is only ever *not* hit for the first node, since it's the only one without a parent.
         if let Some((p1_node, _)) = visited.get_index(p1_index) {
             // Ensure that the move from the parent node to the node we're at is actually valid.
@@ -171,14 +1350,45 @@ where
             // that all moves are valid until we're actually considering moving to them. That saves
             // us from having to check whether we're actually able to move to nodes we never end up
             // considering / visiting.
-            if !is_valid_move(p1_node, p0_node) {
+            let valid = problem.is_valid_move(p1_node, p0_node);
+            let (from, to) = (*p1_node, *p0_node);
+            observer.observe(Event::MoveChecked { from, to, valid });
+
+            // Moves with a fallback are always jumps.
+            match (valid, fallback.is_some()) {
+                (true, true) => observer.observe(Event::JumpAccepted { from, to }),
+                (false, true) => observer.observe(Event::JumpRejected { from, to }),
+                _ => {}
+            }
+
+            if !valid {
                 // If this node has a fallback node defined we want to register that fallback node
                 // as a potential node. We could've also registered this node as pending already
                 // when we first found it, but since the node we're currently on is objectively
                 // better if it can be taken we can defer it until now and avoid pushing more nodes
                 // than necessary to the pending heap.
                 if let Some(fb) = fallback {
-                    add_pending(&mut visited, &mut pending, &mut heuristic, fb.parent, fb.cost, fb.node, None);
+                    // Jumps that skip several ancestors fall back to the jump from the ancestor
+                    // before, and only the last one in the chain falls back to the normal move.
+                    let (parent, cost, node, next) = match find_jump(visited, problem, fb.parent, fb.node, fb.depth) {
+                        Some(jump) => {
+                            let cost = jump_cost(visited, problem, observer, &jump);
+                            (jump.from, cost, jump.to, Some(Fallback { depth: jump.depth - 1, ..fb }))
+                        }
+                        None => (fb.parent, fb.cost, fb.node, None),
+                    };
+
+                    // The visited entry of this node may still be the jump we just rejected, which
+                    // is cheaper than the fallback. It's unreachable now, so the fallback is allowed
+                    // to replace it regardless. The rejected jump itself was never expanded, so no
+                    // other node was reached through it.
+                    let replace = Some(p0_index);
+
+                    let order = (tie_break, &mut *pushed);
+                    if add_pending(visited, pending, observer, order, problem, replace, parent, cost, node, next) {
+                        let from = unsafe { *visited.get_index(parent).unwrap_unchecked().0 };
+                        observer.observe(Event::FallbackPushed { from, to: node });
+                    }
                 }
 
                 // Since the move wasn't valid we're done with this iteration.
@@ -186,85 +1396,283 @@ where
             }
         }
 
+        // At this point we know that the node is actually reachable, so it's a candidate for the
+        // end of a partial path.
+        if partial {
+            let value = problem.progress(p0_node);
+            if best.is_none_or(|(best, _)| value < best) {
+                best = Some((value, p0_index));
+            }
+        }
+
         // If the node we're currently on is considered a valid goal, we're done.
-        if success(p0_node) {
-            // Since we're holding the end piece we need to rebuild the path by walking the trail
-            // of parent indices.
+        if problem.success(p0_node) {
+            observer.observe(Event::GoalFound { node: *p0_node, cost });
 
-            // We'll start by building the path from the end node to the start node.
-            let to_out = |(&n, _)| n;
-            let parent = |&(_, &(p, _)): &_| visited.get_index(p);
-            let mut path = iter::successors(visited.get_index(p0_index), parent).map(to_out).collect::<Vec<_>>();
+            // Since we're holding the end piece we need to rebuild the path by walking the trail
+            // of parent indices, and then return success with the path and the cost of taking it.
+            return Ok(build_path(visited, p0_index, false));
+        }
 
-            // We then need to reverse the path to get the path from the start node to the end node.
-            path.reverse();
+        expansions += 1;
 
-            // And finally, return success with the finished path and the cost of taking it.
-            return Some((path, cost));
-        }
+        visited[p0_index].3 = jump_depth > 1;
+        let (p0_node, _) = unsafe { visited.get_index(p0_index).unwrap_unchecked() };
+        let parent = visited.get_index(p1_index).map(|(&n, _)| n);
+        observer.observe(Event::Expanded { node: *p0_node, parent });
 
         // Since our current node isn't the goal, we expand it by retrieving and registering all
         // nodes that we can get to from it.
-        for (mut node, move_cost) in successors(p0_node) {
+        for (node, move_cost) in problem.successors(p0_node) {
+            let cost = cost + move_cost;
+            let order = (tie_break, &mut *pushed);
+
             // If our p0 is the starting node there's no p1 to jump from, so we default to a
             // pending normal move from the starting node to the successor.
-            let (mut idx, mut cost, mut fallback) = (p0_index, cost + move_cost, None);
-
-            if let Some((p1_node, &(_, p1_cost))) = visited.get_index(p1_index) {
-                // We need to re-grab p0 here since we borrowed `visited` above.
-                let (p0_node, _) = unsafe { visited.get_index(p0_index).unwrap_unchecked() };
-
-                if let Some(jump_node) = jump_check(p1_node, p0_node, &node) {
+            match find_jump(visited, problem, p0_index, node, jump_depth) {
+                Some(jump) => {
                     // Create a fallback node so we can expand into an equivalent of the second
                     // branch in this match if this jump ends up being considered and is invalid.
-                    let backup = Fallback { parent: p0_index, cost: cost + move_cost, node };
+                    let backup = Fallback { parent: p0_index, cost, node, depth: jump.depth - 1 };
 
-                    // Calculate the actual cost of moving to there.
-                    let move_cost = movement_cost(p1_node, &jump_node);
-
-                    // Use p1 as parent and skip over the p0 node entirely.
-                    idx = p1_index;
-                    cost = p1_cost + move_cost;
-                    node = jump_node;
-                    fallback = Some(backup);
+                    // Use the ancestor as parent and skip over the p0 node (and any ancestors in
+                    // between) entirely.
+                    let cost = jump_cost(visited, problem, observer, &jump);
+                    add_pending(
+                        visited,
+                        pending,
+                        observer,
+                        order,
+                        problem,
+                        None,
+                        jump.from,
+                        cost,
+                        jump.to,
+                        Some(backup),
+                    );
+                }
+                None => {
+                    add_pending(visited, pending, observer, order, problem, None, p0_index, cost, node, None);
                 }
             }
+        }
 
-            add_pending(&mut visited, &mut pending, &mut heuristic, idx, cost, node, fallback);
+        // Pruning is only ever needed after adding nodes, and checking here means that the indices
+        // of the current node and its parent aren't needed anymore.
+        if let Some(limit) = memory_limit.filter(|&limit| visited.len() > limit) {
+            let dropped = prune(visited, pending, best.as_mut().map(|(_, index)| index), limit / 2);
+            observer.observe(Event::Pruned { dropped, visited: visited.len() });
         }
     }
 
     // We only end up here if there's no more elements to pop and explore.
-    None
+    give_up(visited, best, FindError::Exhausted)
+}
+
+/// Rebuilds the path to the node at `index` by walking the trail of parent indices.
+fn build_path<N: Copy, C: Copy>(visited: &Visited<N, C>, index: usize, partial: bool) -> Path<N, C> {
+    // We'll start by collecting the indices from the end node to the start node.
+    let parent = |&i: &usize| Some(visited.get_index(i)?.1 .0).filter(|&p| p < visited.len());
+    let mut indices = iter::successors(Some(index), parent).collect::<Vec<_>>();
+
+    // We then need to reverse them to get the path from the start node to the end node.
+    indices.reverse();
+
+    let entry = |i: usize| visited.get_index(i).unwrap();
+    let nodes = indices.iter().map(|&i| *entry(i).0).collect::<Vec<_>>();
+    let costs = indices.iter().map(|&i| entry(i).1 .1).collect::<Vec<_>>();
+    let jumps = indices.iter().skip(1).map(|&i| entry(i).1 .2).collect();
+
+    Path { nodes, cost: *costs.last().unwrap(), partial, costs, jumps, indices }
+}
+
+/// A jump that `jump_check` allows, from the ancestor at index `from` over the one at index `skip`
+/// to `to`, which is `depth` ancestors back from the node it's a successor of.
+struct Jump<N> {
+    from: usize,
+    skip: usize,
+    to: N,
+    depth: usize,
+}
+
+/// Looks for the furthest ancestor of the node at `index` that `to` can be jumped to from, going
+/// back at most `depth` ancestors. Every ancestor is checked with the node `jump_check` returned
+/// for the one before it, and the search stops at the first one it doesn't allow.
+fn find_jump<P: SearchProblem>(
+    visited: &Visited<P::Node, P::Cost>,
+    problem: &mut P,
+    index: usize,
+    mut to: P::Node,
+    depth: usize,
+) -> Option<Jump<P::Node>> {
+    let mut jump = None;
+    let mut skip = index;
+
+    for depth in 1..=depth {
+        let (skip_node, &(from, _, _, _)) = visited.get_index(skip)?;
+        let Some((from_node, _)) = visited.get_index(from) else {
+            break;
+        };
+        let Some(jump_node) = problem.jump_check(from_node, skip_node, &to) else {
+            break;
+        };
+
+        to = jump_node;
+        jump = Some(Jump { from, skip, to, depth });
+        skip = from;
+    }
+
+    jump
+}
+
+/// Calculates the cost of taking `jump`, and lets the observer know it's being offered.
+fn jump_cost<P: SearchProblem>(
+    visited: &Visited<P::Node, P::Cost>,
+    problem: &mut P,
+    observer: &mut impl Observer<P::Node, P::Cost>,
+    jump: &Jump<P::Node>,
+) -> P::Cost {
+    let (from, &(_, from_cost, _, _)) = unsafe { visited.get_index(jump.from).unwrap_unchecked() };
+    let skip = unsafe { *visited.get_index(jump.skip).unwrap_unchecked().0 };
+    observer.observe(Event::JumpOffered { from: *from, skip, to: jump.to });
+
+    from_cost + problem.movement_cost(from, &jump.to)
+}
+
+/// Drops the worst pending nodes until the remaining ones lead back to the start through about
+/// `target` visited nodes, and removes every other visited node. The indices held by the pending
+/// nodes, the visited nodes and `best` are updated to match. Returns the number of dropped nodes.
+fn prune<N: Eq + Hash + Copy, C: Ord + Copy>(
+    visited: &mut Visited<N, C>,
+    pending: &mut BinaryHeap<Pending<C, N>>,
+    best: Option<&mut usize>,
+    target: usize,
+) -> usize {
+    let len = visited.len();
+
+    // Marks the node at `index` and its ancestors as kept, returning how many weren't already.
+    let mut kept = vec![false; len];
+    let keep = |kept: &mut [bool], mut index: usize| {
+        let mut marked = 0;
+        while index < len && !kept[index] {
+            kept[index] = true;
+            marked += 1;
+            index = visited[index].0;
+        }
+        marked
+    };
+
+    let mut count = best.as_deref().map_or(0, |&best| keep(&mut kept, best));
+
+    // Going through the pending nodes in the order they'd be popped in keeps the best ones. The
+    // first one is always kept, since the search would have nowhere to go otherwise.
+    let mut entries = pending.drain().collect::<Vec<_>>();
+    entries.sort_unstable_by(|a, b| b.cmp(a));
+
+    let mut full = false;
+    entries.retain(|entry| {
+        // Stale entries would only be skipped once popped anyway.
+        if full || visited[entry.index].1 != entry.cost || visited[entry.index].3 {
+            return false;
+        }
+
+        count += keep(&mut kept, entry.index);
+        if let Some(fb) = &entry.fallback {
+            count += keep(&mut kept, fb.parent);
+        }
+
+        full = count >= target;
+        true
+    });
+
+    // The kept nodes stay in the same order, so their new index is the number of kept nodes before them.
+    let remap = kept
+        .iter()
+        .scan(0, |next, &kept| {
+            *next += usize::from(kept);
+            Some(*next - usize::from(kept))
+        })
+        .collect::<Vec<_>>();
+
+    let mut index = 0;
+    visited.retain(|_, _| {
+        index += 1;
+        kept[index - 1]
+    });
+
+    for (parent, _, _, _) in visited.values_mut() {
+        if *parent < len {
+            *parent = remap[*parent];
+        }
+    }
+
+    pending.extend(entries.into_iter().map(|mut entry| {
+        entry.index = remap[entry.index];
+        if let Some(fb) = &mut entry.fallback {
+            fb.parent = remap[fb.parent];
+        }
+        entry
+    }));
+
+    if let Some(best) = best {
+        *best = remap[*best];
+    }
+
+    len - visited.len()
 }
 
-fn add_pending<N: Eq + Hash + Copy, C: Zero + Ord + Copy>(
-    visited: &mut FxIndexMap<N, (usize, C)>,
+/// Registers `node` as pending, unless a cheaper way to it is already known. Returns whether the
+/// node was pushed.
+///
+/// `order` is the tie-breaking policy of the search, along with the number of nodes it has pushed.
+/// `replace` is the index of a node that's known to be unreachable the way its entry says, which
+/// lets it be replaced even by a more expensive way to it.
+#[allow(clippy::too_many_arguments)]
+fn add_pending<N: Eq + Hash + Copy, C: Zero + Ord + Copy, P: SearchProblem<Node = N, Cost = C>>(
+    visited: &mut Visited<N, C>,
     pending: &mut BinaryHeap<Pending<C, N>>,
-    mut heuristic: impl FnMut(&N) -> C,
+    observer: &mut impl Observer<N, C>,
+    (tie_break, pushed): (TieBreak, &mut usize),
+    problem: &mut P,
+    replace: Option<usize>,
     n_parent_idx: usize,
     cost: C,
     node: N,
     fallback: Option<Fallback<C, N>>,
-) {
+) -> bool {
     let (heuristic_value, index) = match visited.entry(node) {
         Vacant(entry) => {
-            let out = (heuristic(entry.key()), entry.index());
-            entry.insert((n_parent_idx, cost));
+            let out = (problem.heuristic(entry.key()), entry.index());
+            entry.insert((n_parent_idx, cost, fallback.is_some(), false));
             out
         }
-        Occupied(mut entry) if cost < entry.get().1 => {
-            let out = (heuristic(entry.key()), entry.index());
-            entry.insert((n_parent_idx, cost));
+        Occupied(mut entry) if !entry.get().3 && (cost < entry.get().1 || replace == Some(entry.index())) => {
+            let out = (problem.heuristic(entry.key()), entry.index());
+            entry.insert((n_parent_idx, cost, fallback.is_some(), false));
             out
         }
 
-        // If the entry is occupied with a lower cost (or same-cost) alternative we'll just keep
-        // that one.
-        Occupied(_) => return,
+        // If the entry is occupied with a lower cost (or same-cost) alternative, or it has already
+        // been closed, we'll just keep that one.
+        Occupied(_) => return false,
+    };
+
+    let tie = match tie_break {
+        TieBreak::HigherCost => Tie::Greater(cost),
+        TieBreak::LowerCost => Tie::Less(Reverse(cost)),
+        TieBreak::Fifo => Tie::Earlier(Reverse(*pushed)),
+        TieBreak::Lifo => Tie::Later(*pushed),
+        TieBreak::Custom => Tie::Greater(problem.tie_break(&node)),
     };
+    *pushed += 1;
+
+    pending.push(Pending { estimated_cost: cost + heuristic_value, cost, tie, index, fallback });
 
-    pending.push(Pending { estimated_cost: cost + heuristic_value, cost, index, fallback });
+    let parent = visited.get_index(n_parent_idx).map(|(&n, _)| n);
+    let (pending, visited) = (pending.len(), visited.len());
+    observer.observe(Event::Pushed { node, parent, cost, pending, visited });
+
+    true
 }
 
 struct Pending<K, N> {
@@ -272,6 +1680,8 @@ struct Pending<K, N> {
     estimated_cost: K,
     // Cost to get to here.
     cost: K,
+    // What to order by when the estimated cost is the same as that of another node.
+    tie: Tie<K>,
     // Index of the node in the visited list.
     index: usize,
 
@@ -345,7 +1755,16 @@ struct Pending<K, N> {
     fallback: Option<Fallback<K, N>>,
 }
 
-#[derive(PartialEq, Eq)]
+// The value pending nodes with the same estimated cost are ordered by, where the greater one is
+// popped first. Every node in a search uses the same variant, as picked by its `TieBreak`.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Tie<K> {
+    Greater(K),
+    Less(Reverse<K>),
+    Later(usize),
+    Earlier(Reverse<usize>),
+}
+
 struct Fallback<K, N> {
     // Since our fallbacks always involve swapping out the parent node, we're storing the index of
     // the new parent here. This is how we keep track of what the actual fallback move is. If we
@@ -368,17 +1787,20 @@ struct Fallback<K, N> {
     // and the easiest way to do that is simply to store the original state as part of the fallback
     // data.
     node: N,
+
+    // Jumps can skip several ancestors, in which case a rejected jump first falls back to the jump
+    // from the ancestor before it. This is how many ancestors that next jump may skip, where `0`
+    // means that the fallback is the normal move. The jump itself is looked up again when the
+    // fallback is taken, rather than stored here for every jump that's never rejected.
+    depth: usize,
 }
 
-impl<K: PartialEq, N: Eq> Eq for Pending<K, N> {}
-impl<K: PartialEq, N: PartialEq> PartialEq for Pending<K, N> {
+// Pending nodes are only ever compared to order the heap, so two of them are equal whenever
+// neither one would be popped before the other, whatever their fallbacks are.
+impl<K: Ord, N: Eq> Eq for Pending<K, N> {}
+impl<K: Ord, N: Eq> PartialEq for Pending<K, N> {
     fn eq(&self, other: &Self) -> bool {
-        // A node also has to have the same fallback case to be considered to be the same node for
-        // now. This is suboptimal, but the alternative is that we might otherwise abandon fallback
-        // cases that we're not intending to abandon. This is something that we might want to look
-        // at further to see if we can simplify, since that would reduce the number of considered
-        // nodes at any given point in time, potentially saving us some memory.
-        self.estimated_cost == other.estimated_cost && self.cost == other.cost && self.fallback == other.fallback
+        self.cmp(other) == Ordering::Equal
     }
 }
 
@@ -391,8 +1813,697 @@ impl<K: Ord, N: Eq> PartialOrd for Pending<K, N> {
 impl<K: Ord, N: Eq> Ord for Pending<K, N> {
     fn cmp(&self, other: &Self) -> Ordering {
         match other.estimated_cost.cmp(&self.estimated_cost) {
-            Ordering::Equal => self.cost.cmp(&other.cost),
+            Ordering::Equal => self.tie.cmp(&other.tie),
             s => s,
         }
     }
 }
+
+// The straight neighbors of a cell come first, followed by the diagonal ones.
+#[cfg(test)]
+const GRID_NEIGHBORS: [(i32, i32); 8] = [(1, 0), (0, 1), (-1, 0), (0, -1), (1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+#[cfg(test)]
+fn grid_successors(&(x, y): &(i32, i32)) -> impl IntoIterator<Item = ((i32, i32), crate::Cost)> {
+    GRID_NEIGHBORS[..4].iter().map(move |&(dx, dy)| ((x + dx, y + dy), 1.0.into()))
+}
+
+#[cfg(test)]
+pub(crate) fn grid_dist(&(x0, y0): &(i32, i32), &(x1, y1): &(i32, i32)) -> crate::Cost {
+    (((x1 - x0).pow(2) + (y1 - y0).pow(2)) as f32).sqrt().into()
+}
+
+/// A square grid of cells around the origin, shared by the tests of everything that searches.
+///
+/// Moves are checked by sampling the cells along the way, so that jumps can't pass through blocked
+/// cells either.
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub(crate) struct GridProblem {
+    pub goal: (i32, i32),
+    // How far the grid reaches from the origin along either axis.
+    pub size: i32,
+    pub blocked: std::collections::HashSet<(i32, i32)>,
+    // Cells that take three times as long to move to, which the heuristic doesn't know about.
+    pub slow: std::collections::HashSet<(i32, i32)>,
+    pub diagonals: bool,
+    pub jumps: bool,
+    // The number of moves that were validated.
+    pub checks: usize,
+}
+
+#[cfg(test)]
+impl GridProblem {
+    pub fn new(size: i32, goal: (i32, i32)) -> Self {
+        GridProblem { goal, size, ..GridProblem::default() }
+    }
+
+    pub fn with_blocked(mut self, cells: impl IntoIterator<Item = (i32, i32)>) -> Self {
+        self.blocked.extend(cells);
+        self
+    }
+
+    pub fn with_slow(mut self, cells: impl IntoIterator<Item = (i32, i32)>) -> Self {
+        self.slow.extend(cells);
+        self
+    }
+
+    pub fn with_diagonals(mut self) -> Self {
+        self.diagonals = true;
+        self
+    }
+
+    pub fn with_jumps(mut self) -> Self {
+        self.jumps = true;
+        self
+    }
+
+    /// The cells in a square of `size` around the origin that `f` returns `true` for.
+    pub fn cells(size: i32, mut f: impl FnMut(i32, i32) -> bool) -> impl Iterator<Item = (i32, i32)> {
+        (-size..=size).flat_map(move |x| (-size..=size).map(move |y| (x, y))).filter(move |&(x, y)| f(x, y))
+    }
+
+    /// Samples the cells a move passes through, which is plenty for the short moves in the tests.
+    pub fn cells_between(&(x0, y0): &(i32, i32), &(x1, y1): &(i32, i32)) -> impl Iterator<Item = (i32, i32)> {
+        (0..=100).map(move |i| {
+            let t = i as f32 / 100.0;
+            let x = (x0 as f32 + (x1 - x0) as f32 * t).round() as i32;
+            let y = (y0 as f32 + (y1 - y0) as f32 * t).round() as i32;
+            (x, y)
+        })
+    }
+
+    /// A corridor along the x axis, three cells wide.
+    pub fn corridor(goal: (i32, i32)) -> Self {
+        GridProblem::new(10, goal).with_blocked(GridProblem::cells(10, |_, y| y.abs() == 2))
+    }
+
+    /// A pillar in the middle, with a way around it on either side.
+    pub fn field() -> Self {
+        GridProblem::new(8, (6, 0))
+            .with_diagonals()
+            .with_blocked(GridProblem::cells(8, |x, y| x.abs() <= 1 && y.abs() <= 3))
+    }
+
+    /// Wading through the swamp in the middle is slow, which the heuristic doesn't know about, so
+    /// greedy searches walk straight into it rather than around it.
+    pub fn swamp() -> Self {
+        GridProblem::new(12, (6, 0))
+            .with_diagonals()
+            .with_slow(GridProblem::cells(12, |x, y| x.abs() <= 2 && y.abs() <= 5))
+    }
+
+    /// A wall across the middle with a gap near the top, and a box around (10, -10) with no way in.
+    pub fn hall(goal: (i32, i32)) -> Self {
+        let blocked = GridProblem::cells(12, |x, y| (x == 0 && y < 9) || (x - 10).abs().max((y + 10).abs()) == 2);
+        GridProblem::new(12, goal).with_diagonals().with_jumps().with_blocked(blocked)
+    }
+
+    /// A yard fenced in 10 cells around the middle, with a wall to the right of the start. Its goal
+    /// isn't used, since the goals are passed to the search instead.
+    pub fn yard() -> Self {
+        GridProblem::new(10, (0, 0)).with_blocked(GridProblem::cells(10, |x, y| x == 2 && y.abs() <= 4))
+    }
+
+    /// Walls every 8 cells, with a door at alternating ends.
+    pub fn rooms(goal: (i32, i32)) -> Self {
+        let door = |x: i32| if x.rem_euclid(16) == 0 { 18 } else { -18 };
+        GridProblem::new(20, goal)
+            .with_diagonals()
+            .with_blocked(GridProblem::cells(20, |x, y| x.rem_euclid(8) == 0 && y != door(x)))
+    }
+}
+
+#[cfg(test)]
+impl SearchProblem for GridProblem {
+    type Node = (i32, i32);
+    type Cost = crate::Cost;
+    type Successors = Vec<((i32, i32), crate::Cost)>;
+
+    fn successors(&mut self, &(x, y): &(i32, i32)) -> Self::Successors {
+        let (neighbors, size) = (if self.diagonals { &GRID_NEIGHBORS[..] } else { &GRID_NEIGHBORS[..4] }, self.size);
+        neighbors
+            .iter()
+            .map(|(dx, dy)| (x + dx, y + dy))
+            .filter(|&(x, y)| x.abs() <= size && y.abs() <= size)
+            .map(|to| (to, self.movement_cost(&(x, y), &to)))
+            .collect()
+    }
+
+    fn is_valid_move(&mut self, from: &(i32, i32), to: &(i32, i32)) -> bool {
+        self.checks += 1;
+        GridProblem::cells_between(from, to).all(|cell| !self.blocked.contains(&cell))
+    }
+
+    fn movement_cost(&mut self, from: &(i32, i32), to: &(i32, i32)) -> crate::Cost {
+        let dist = grid_dist(from, to);
+        if self.slow.contains(to) {
+            dist * 3.0
+        } else {
+            dist
+        }
+    }
+
+    fn heuristic(&mut self, node: &(i32, i32)) -> crate::Cost {
+        grid_dist(node, &self.goal)
+    }
+
+    fn success(&mut self, node: &(i32, i32)) -> bool {
+        *node == self.goal
+    }
+
+    fn jump_check(&mut self, _: &(i32, i32), _: &(i32, i32), to: &(i32, i32)) -> Option<(i32, i32)> {
+        self.jumps.then_some(*to)
+    }
+}
+
+#[test]
+fn find_bounded_stops_at_expansion_limit() {
+    let goal = (20, 0);
+    let budget = Budget { max_expansions: Some(5), ..Budget::default() };
+
+    let result = find_bounded(
+        (0, 0),
+        &budget,
+        grid_successors,
+        |_, _| true,
+        grid_dist,
+        |_| 0.0.into(),
+        |&n| n == goal,
+        |_, _, _| None,
+    );
+
+    assert_eq!(result, Err(FindError::BudgetExceeded(Limit::Expansions)));
+}
+
+#[test]
+fn find_bounded_stops_when_cancelled() {
+    let goal = (20, 0);
+    let budget = Budget { cancel: Some(Arc::new(AtomicBool::new(true))), ..Budget::default() };
+
+    let result = find_bounded(
+        (0, 0),
+        &budget,
+        grid_successors,
+        |_, _| true,
+        grid_dist,
+        |n| grid_dist(n, &goal),
+        |&n| n == goal,
+        |_, _, _| None,
+    );
+
+    assert_eq!(result, Err(FindError::BudgetExceeded(Limit::Cancelled)));
+}
+
+#[test]
+fn find_bounded_finds_path_within_budget() {
+    let goal = (5, 0);
+    let budget = Budget {
+        max_expansions: Some(100),
+        deadline: Some(Instant::now() + Duration::from_secs(60)),
+        ..Budget::default()
+    };
+
+    let result = find_bounded(
+        (0, 0),
+        &budget,
+        grid_successors,
+        |_, _| true,
+        grid_dist,
+        |n| grid_dist(n, &goal),
+        |&n| n == goal,
+        |_, _, _| None,
+    );
+
+    let Path { nodes: path, .. } = result.unwrap();
+    assert_eq!(path.first(), Some(&(0, 0)));
+    assert_eq!(path.last(), Some(&goal));
+}
+
+#[test]
+fn find_reports_why_no_path_was_found() {
+    let goal = (5, 0);
+    let bounded = |(x, y): (i32, i32)| x.abs() <= 2 && y.abs() <= 2;
+    let search = |initialize: &[((i32, i32), crate::Cost)]| {
+        find_with_init(
+            (0, 0),
+            initialize.iter().copied(),
+            grid_successors,
+            |_, &to| bounded(to),
+            grid_dist,
+            |n| grid_dist(n, &goal),
+            |&n| n == goal,
+            |_, _, _| None,
+        )
+    };
+
+    assert_eq!(search(&[((1, 0), 1.0.into())]), Err(FindError::Exhausted));
+    assert_eq!(search(&[((3, 0), 1.0.into())]), Err(FindError::NoValidInitialMoves));
+    assert_eq!(search(&[]), Err(FindError::InvalidInput));
+}
+
+#[test]
+fn find_partial_returns_path_to_closest_node_when_unreachable() {
+    let goal = (5, 0);
+
+    // A wall at x = 3 that fully separates the start from the goal within a small arena.
+    let in_arena = |&(x, y): &(i32, i32)| x.abs() <= 5 && y.abs() <= 5 && x != 3;
+
+    let path = find_partial(
+        (0, 0),
+        &Budget::default(),
+        grid_successors,
+        |_, to| in_arena(to),
+        grid_dist,
+        |n| grid_dist(n, &goal),
+        |&n| n == goal,
+        |_, _, _| None,
+        |n| grid_dist(n, &goal),
+    );
+
+    assert!(path.partial);
+    assert_eq!(path.nodes.first(), Some(&(0, 0)));
+    assert_eq!(path.nodes.last(), Some(&(2, 0)));
+    assert_eq!(path.cost.0, 2.0);
+}
+
+#[test]
+fn find_partial_returns_full_path_when_reachable() {
+    let goal = (5, 0);
+
+    let path = find_partial(
+        (0, 0),
+        &Budget { max_expansions: Some(1000), ..Budget::default() },
+        grid_successors,
+        |_, _| true,
+        grid_dist,
+        |n| grid_dist(n, &goal),
+        |&n| n == goal,
+        |_, _, _| None,
+        |n| grid_dist(n, &goal),
+    );
+
+    assert!(!path.partial);
+    assert_eq!(path.nodes.last(), Some(&goal));
+}
+
+#[test]
+fn find_partial_returns_path_when_budget_is_exceeded() {
+    let goal = (50, 0);
+
+    let path = find_partial(
+        (0, 0),
+        &Budget { max_expansions: Some(10), ..Budget::default() },
+        grid_successors,
+        |_, _| true,
+        grid_dist,
+        |n| grid_dist(n, &goal),
+        |&n| n == goal,
+        |_, _, _| None,
+        |n| grid_dist(n, &goal),
+    );
+
+    assert!(path.partial);
+    assert_eq!(path.nodes.first(), Some(&(0, 0)));
+    assert!(path.nodes.len() > 1);
+}
+
+#[test]
+fn search_context_can_be_reused() {
+    let mut ctx = SearchContext::new();
+
+    for goal in [(5, 0), (-3, 4), (5, 0)] {
+        let found = ctx.find(
+            (0, 0),
+            grid_successors,
+            |_, _| true,
+            grid_dist,
+            |n| grid_dist(n, &goal),
+            |&n| n == goal,
+            |_, _, _| None,
+        );
+        let fresh = find(
+            (0, 0),
+            grid_successors,
+            |_, _| true,
+            grid_dist,
+            |n| grid_dist(n, &goal),
+            |&n| n == goal,
+            |_, _, _| None,
+        );
+
+        assert_eq!(found, fresh);
+        assert_eq!(found.unwrap().nodes.last(), Some(&goal));
+    }
+}
+
+#[test]
+fn search_problem_matches_closure_api() {
+    let goal = (6, 1);
+    let in_corridor = |_: &(i32, i32), &(_, y): &(i32, i32)| y.abs() <= 1;
+
+    let mut ctx = SearchContext::new();
+    let found = ctx.search(&mut GridProblem::corridor(goal), (0, 0), &Budget::default()).unwrap();
+    let fresh =
+        find((0, 0), grid_successors, in_corridor, grid_dist, |n| grid_dist(n, &goal), |&n| n == goal, |_, _, _| None);
+
+    assert_eq!(Ok(found.clone()), fresh);
+    assert!(found.nodes.iter().all(|&(_, y)| y.abs() <= 1));
+}
+
+#[test]
+fn search_steps_to_same_path_as_find() {
+    let goal = (6, 1);
+    let in_corridor = |_: &(i32, i32), &(_, y): &(i32, i32)| y.abs() <= 1;
+    let heuristic = |n: &(i32, i32)| grid_dist(n, &goal);
+    let mut search =
+        Search::new((0, 0), grid_successors, in_corridor, grid_dist, heuristic, |&n| n == goal, |_, _, _| None);
+
+    let mut steps = 1;
+    let path = loop {
+        match search.step(1) {
+            Step::Pending => steps += 1,
+            Step::Found(path) => break path,
+            Step::Exhausted => panic!("search exhausted"),
+        }
+    };
+
+    let found = find((0, 0), grid_successors, in_corridor, grid_dist, heuristic, |&n| n == goal, |_, _, _| None);
+    assert_eq!(path.cost, found.unwrap().cost);
+    assert!(path.nodes.iter().all(|&(_, y)| y.abs() <= 1));
+    assert!(steps > 1);
+
+    // Once done, the search keeps reporting the same outcome.
+    assert_eq!(search.step(1), Step::Found(path));
+}
+
+#[test]
+fn search_steps_until_exhausted() {
+    let in_room = |_: &(i32, i32), &(x, y): &(i32, i32)| x.abs() <= 2 && y.abs() <= 2;
+    let mut search =
+        Search::new((0, 0), grid_successors, in_room, grid_dist, |_| 0.0.into(), |_| false, |_, _, _| None);
+    let budget = Budget { cancel: Some(Arc::new(AtomicBool::new(true))), ..Budget::default() };
+
+    assert_eq!(search.step_within(&budget), Step::Pending);
+    assert_eq!(search.step(usize::MAX), Step::Exhausted);
+    assert_eq!(search.step(1), Step::Exhausted);
+}
+
+#[test]
+fn search_path_describes_its_segments() {
+    let goal = (3, 2);
+    let mut problem = FnProblem::new(
+        grid_successors,
+        |_: &_, _: &_| true,
+        grid_dist,
+        |n: &_| grid_dist(n, &goal),
+        |&n: &_| n == goal,
+        |_: &_, _: &_, &to: &_| Some(to),
+    );
+
+    let mut ctx = SearchContext::new();
+    let path = ctx.search(&mut problem, (0, 0), &Budget::default()).unwrap();
+
+    assert_eq!(path.nodes, [(0, 0), (3, 2)]);
+    assert_eq!(path.costs, [0.0.into(), grid_dist(&(0, 0), &goal)]);
+    assert_eq!(path.indices.first(), Some(&0));
+
+    let segments = path.segments().collect::<Vec<_>>();
+    assert_eq!(segments, [Segment { from: (0, 0), to: goal, cost: path.cost, jump: true }]);
+
+    // Without jumps every move is a plain move to a successor.
+    let mut problem = GridProblem::corridor((3, 1));
+    let path = ctx.search(&mut problem, (0, 0), &Budget::default()).unwrap();
+
+    assert_eq!(path.costs.len(), path.nodes.len());
+    assert!(path.segments().all(|segment| !segment.jump && segment.cost.0 == 1.0));
+    assert_eq!(path.segments().fold(0.0, |cost, segment| cost + segment.cost.0), path.cost.0);
+}
+
+#[test]
+fn search_context_records_stats() {
+    use crate::missile::{Missile, MissileSet};
+    use crate::pos::Pos;
+    use ultraviolet::Vec2;
+
+    let (move_speed, pawn_size, step_size) = (325.0, 55.0, 50.0);
+    let step_time = step_size / move_speed;
+    let target = Pos::new(1000.0, 1000.0, 0.0);
+
+    // A wall of slow missiles across the straight line to the target.
+    let mut missiles = FxIndexMap::default();
+    for i in 0..5 {
+        let offset = i as f32 * 150.0;
+        let missile = Missile::new(
+            0.0,
+            Vec2::new(200.0 + offset, 800.0 - offset),
+            Vec2::new(300.0 + offset, 900.0 - offset),
+            60.0,
+            10.0,
+        );
+        missiles.insert(i, missile);
+    }
+    let mis = MissileSet(missiles);
+
+    let mut ctx = SearchContext::with_observer(Stats::default());
+    let run = |ctx: &mut SearchContext<_, _, Stats>| {
+        ctx.find(
+            Pos::new(0.0, 0.0, 0.0),
+            |pos| pos.successors(step_time, step_size),
+            |beg, end| mis.collides_points(beg, end, move_speed, pawn_size).is_none(),
+            |beg, end| (beg.dist(end) / step_size).into(),
+            |pos| (pos.dist(&target) / step_size).into(),
+            |pos| pos.is_same_pos(&target, step_size),
+            |n1, n2, to_node| Pos::jump_calc(n1, n2, to_node, move_speed),
+        )
+    };
+
+    run(&mut ctx).unwrap();
+    let stats = *ctx.observer();
+
+    assert!(stats.expanded > 0);
+    assert!(stats.jumps > 0);
+    assert!(stats.fallbacks > 0);
+    assert!(stats.pushed >= stats.expanded);
+    assert!(stats.valid_move_checks >= stats.fallbacks);
+    assert_eq!(stats.peak_visited, ctx.visited.len());
+
+    // Stats are reset between searches rather than accumulated.
+    run(&mut ctx).unwrap();
+    assert_eq!(*ctx.observer(), stats);
+}
+
+#[test]
+fn search_stays_within_memory_limit() {
+    let goal = (10, 0);
+
+    // A wall between the start and the goal that has to be walked around.
+    let is_valid_move = |_: &(i32, i32), &(x, y): &(i32, i32)| x.abs() <= 15 && y.abs() <= 15 && !(x == 5 && y < 10);
+    let find = |ctx: &mut SearchContext<_, _, Stats>| {
+        ctx.find(
+            (0, 0),
+            grid_successors,
+            is_valid_move,
+            grid_dist,
+            |n| grid_dist(n, &goal),
+            |&n| n == goal,
+            |_, _, _| None,
+        )
+    };
+
+    let mut ctx = SearchContext::with_observer(Stats::default());
+    let optimal = find(&mut ctx).unwrap().cost;
+    let unlimited = *ctx.observer();
+
+    let mut ctx = SearchContext::with_observer(Stats::default()).with_memory_limit(40);
+    let Path { nodes: path, cost, .. } = find(&mut ctx).unwrap();
+    let stats = *ctx.observer();
+
+    assert_eq!((path.first(), path.last()), (Some(&(0, 0)), Some(&goal)));
+    assert!(path.windows(2).all(|w| grid_dist(&w[0], &w[1]).0 == 1.0 && is_valid_move(&w[0], &w[1])));
+    assert!(cost >= optimal);
+
+    assert!(stats.pruned > 0);
+    assert!(stats.peak_visited < unlimited.peak_visited);
+    assert!(stats.peak_visited <= 40 + 4 + path.len());
+}
+
+#[cfg(test)]
+fn search_direct_past_wall(goals: &[(i32, i32)]) -> (Route<(i32, i32), crate::Cost>, Stats) {
+    // A wall across x = 3 that has to be walked around, with every cell at x = 6 being a goal.
+    let is_valid_move = |from: &(i32, i32), to: &(i32, i32)| {
+        GridProblem::cells_between(from, to).all(|(x, y)| !(x == 3 && y.abs() <= 2))
+    };
+    let heuristic = |&(x, _): &(i32, i32)| crate::Cost::from((6 - x).abs() as f32);
+    let jump_check = |_: &(i32, i32), _: &(i32, i32), to: &(i32, i32)| Some(*to);
+    let mut problem =
+        FnProblem::new(grid_successors, is_valid_move, grid_dist, heuristic, |n: &(i32, i32)| n.0 == 6, jump_check);
+
+    let mut ctx = SearchContext::with_observer(Stats::default());
+    let route = ctx.search_direct(&mut problem, (0, 0), goals.iter().copied(), &Budget::default()).unwrap();
+    (route, *ctx.observer())
+}
+
+#[test]
+fn search_direct_moves_to_cheapest_open_goal() {
+    // (6, 0) is behind the wall and (7, 0) isn't a goal, so (6, -6) is the cheapest one left.
+    let (route, stats) = search_direct_past_wall(&[(6, 8), (6, 0), (7, 0), (6, -6)]);
+    let Route::Direct(path) = route else { panic!("expected a direct route, got {route:?}") };
+
+    assert_eq!(path.nodes, [(0, 0), (6, -6)]);
+    assert_eq!(path.cost, grid_dist(&(0, 0), &(6, -6)));
+    assert_eq!(path.segments().count(), 1);
+    assert_eq!(path.jumps, [false]);
+    assert!(!path.partial);
+    assert_eq!((stats.valid_move_checks, stats.expanded), (2, 0));
+}
+
+#[test]
+fn search_direct_falls_back_to_search() {
+    let (route, stats) = search_direct_past_wall(&[(6, 0), (7, 0)]);
+    let Route::Searched(path) = route else { panic!("expected a searched route, got {route:?}") };
+
+    assert_eq!(path.nodes.first(), Some(&(0, 0)));
+    assert_eq!(path.nodes.last().map(|n| n.0), Some(6));
+    assert!(path.nodes.len() > 2);
+    assert!(stats.expanded > 0);
+
+    let (route, _) = search_direct_past_wall(&[]);
+    assert_eq!(route, Route::Searched(path));
+}
+
+#[cfg(test)]
+struct Lattice {
+    goal: (i32, i32),
+}
+
+#[cfg(test)]
+impl SearchProblem for Lattice {
+    type Node = (i32, i32);
+    type Cost = crate::Cost;
+    type Successors = [((i32, i32), crate::Cost); 4];
+
+    fn successors(&mut self, node: &(i32, i32)) -> Self::Successors {
+        [(1, 0), (0, 1), (-1, 0), (0, -1)].map(|(dx, dy)| ((node.0 + dx, node.1 + dy), 1.0.into()))
+    }
+
+    fn is_valid_move(&mut self, _: &(i32, i32), _: &(i32, i32)) -> bool {
+        true
+    }
+
+    fn movement_cost(&mut self, from: &(i32, i32), to: &(i32, i32)) -> crate::Cost {
+        grid_dist(from, to)
+    }
+
+    // Every node on the way to the goal is estimated to be equally good, so the order they're
+    // expanded in comes down to tie-breaking.
+    fn heuristic(&mut self, &(x, y): &(i32, i32)) -> crate::Cost {
+        (((self.goal.0 - x).abs() + (self.goal.1 - y).abs()) as f32).into()
+    }
+
+    fn success(&mut self, node: &(i32, i32)) -> bool {
+        *node == self.goal
+    }
+
+    fn tie_break(&mut self, &(_, y): &(i32, i32)) -> crate::Cost {
+        (y as f32).into()
+    }
+}
+
+#[test]
+fn tie_break_changes_expansions_but_not_cost() {
+    let run = |tie_break| {
+        let mut ctx = SearchContext::with_observer(Stats::default()).with_tie_break(tie_break);
+        let path = ctx.search(&mut Lattice { goal: (6, 6) }, (0, 0), &Budget::default()).unwrap();
+        (path.cost, ctx.observer().expanded)
+    };
+
+    let (cost, expanded) = run(TieBreak::HigherCost);
+    assert_eq!(cost.0, 12.0);
+
+    for tie_break in [TieBreak::LowerCost, TieBreak::Fifo, TieBreak::Lifo, TieBreak::Custom] {
+        assert_eq!(run(tie_break).0, cost);
+    }
+
+    // Preferring the cheapest nodes goes through every node that's equally good before reaching the
+    // goal, while preferring the most expensive ones heads straight for it.
+    assert_eq!(expanded, 12);
+    assert!(run(TieBreak::LowerCost).1 > 40);
+    assert!(run(TieBreak::Fifo).1 > expanded);
+}
+
+#[test]
+fn tie_break_can_be_custom() {
+    let mut ctx = SearchContext::new().with_tie_break(TieBreak::Custom);
+    let path = ctx.search(&mut Lattice { goal: (3, 3) }, (0, 0), &Budget::default()).unwrap();
+
+    // Going up is preferred, so the path only turns right once it's level with the goal.
+    assert_eq!(path.nodes, [(0, 0), (0, 1), (0, 2), (0, 3), (1, 3), (2, 3), (3, 3)]);
+}
+
+#[test]
+fn observer_sees_search_events() {
+    let goal = (3, 2);
+
+    let mut ctx = SearchContext::with_observer((Stats::default(), Vec::new()));
+    let Path { nodes: path, cost, .. } = ctx
+        .find(
+            (0, 0),
+            grid_successors,
+            |_, _| true,
+            grid_dist,
+            |n| grid_dist(n, &goal),
+            |&n| n == goal,
+            |_, _, &to| Some(to),
+        )
+        .unwrap();
+
+    let (stats, events) = ctx.observer();
+
+    assert_eq!(events.first(), Some(&Event::Started { start: (0, 0) }));
+    assert_eq!(events.last(), Some(&Event::GoalFound { node: goal, cost }));
+    assert_eq!(path.last(), Some(&goal));
+
+    let count = |f: fn(&Event<_, _>) -> bool| events.iter().filter(|e| f(e)).count();
+    assert_eq!(count(|e| matches!(e, Event::Expanded { .. })), stats.expanded);
+    assert_eq!(count(|e| matches!(e, Event::Pushed { .. })), stats.pushed);
+    assert_eq!(count(|e| matches!(e, Event::JumpOffered { .. })), stats.jumps);
+    assert!(count(|e| matches!(e, Event::JumpAccepted { .. })) > 0);
+}
+
+// A line of nodes where moves of any length are allowed, except for the move from 0 to 2, and
+// longer moves are relatively cheaper.
+#[cfg(test)]
+fn find_on_line(ctx: &mut SearchContext<i32, crate::Cost>) -> Result<Path<i32, crate::Cost>, FindError> {
+    let cost = |a: &i32, b: &i32| crate::Cost::from(((b - a).abs() as f32).sqrt());
+    ctx.find(
+        0,
+        |&n| [n - 1, n + 1].into_iter().filter(|n| (0..=6).contains(n)).map(|n| (n, 1.0.into())),
+        |&a, &b| (a, b) != (0, 2),
+        cost,
+        |_| 0.0.into(),
+        |&n| n == 6,
+        |_, _, &to| Some(to),
+    )
+}
+
+#[test]
+fn rejected_jump_falls_back_to_the_same_node() {
+    // The jump from 0 to 2 is rejected, so 2 has to be reached through 1 instead, after which every
+    // other node can be jumped to from 1.
+    let path = find_on_line(&mut SearchContext::new()).unwrap();
+    assert_eq!(path.nodes, [0, 1, 6]);
+}
+
+#[test]
+fn deeper_jumps_skip_several_ancestors() {
+    // Once 2 is reached through 1, jumping to 3 can skip both 2 and 1.
+    let deep = find_on_line(&mut SearchContext::new().with_jump_depth(2)).unwrap();
+    assert_eq!(deep.nodes, [0, 6]);
+
+    let shallow = find_on_line(&mut SearchContext::new()).unwrap();
+    assert!(deep.cost < shallow.cost);
+
+    let path = find_on_line(&mut SearchContext::new().with_jump_depth(0)).unwrap();
+    assert_eq!(path.nodes, [0, 1, 2, 3, 4, 5, 6]);
+}
@@ -0,0 +1,216 @@
+//! Chasing down a target that moves along a known trajectory.
+//!
+//! A fixed goal position doesn't work for a target that's on the move, since by the time a pawn
+//! gets to where the target was, it's somewhere else. A [`Pursuit`] instead succeeds wherever the
+//! pawn catches up with the target at the time it's there. The target moves like a [`Missile`]
+//! does, so its trajectory and size can be described the same way as the obstacles.
+//!
+//! The cost of a path is the time it takes, which is what makes the earliest possible intercept a
+//! good heuristic. It's found by assuming the pawn could head straight for the target without
+//! anything in the way, and solving for the first time the target is in reach.
+
+use ultraviolet::Vec2;
+
+use crate::missile::{Missile, MissileSet};
+use crate::pathfind::SearchProblem;
+use crate::pos::Pos;
+use crate::Cost;
+
+/// A target moving along the trajectory of a [`Missile`], staying at its origin until it spawns and
+/// at its target once it gets there.
+#[derive(Copy, Clone, Debug)]
+pub struct MovingTarget(pub Missile);
+
+impl MovingTarget {
+    /// Returns `true` if a pawn at `pos` touches the target.
+    #[must_use]
+    pub fn caught(&self, pos: &Pos, pawn_size: f32) -> bool {
+        (self.0.position(pos.time()) - pos.vec()).mag_sq() < (self.0.radius + pawn_size).powi(2)
+    }
+
+    /// The earliest time a pawn at `pos` moving at `move_speed` could catch the target, if nothing
+    /// were in its way, or `None` if it can't catch it at all, such as when it can't move.
+    #[must_use]
+    pub fn intercept_time(&self, pos: &Pos, move_speed: f32, pawn_size: f32) -> Option<f32> {
+        let Missile { time_beg, time_end, origin, target, time_offset, .. } = self.0;
+        let reach = self.0.radius + pawn_size;
+        let now = pos.time();
+
+        // The target waits at its origin, moves towards its target, and then waits there. Each part
+        // is described by where the target would be at `now` if it had always moved that way, its
+        // velocity, and the range of times it lasts for.
+        let parts = [
+            (origin, Vec2::zero(), f32::NEG_INFINITY, time_beg),
+            (origin + time_offset * (now - time_beg), time_offset, time_beg, time_end),
+            (target, Vec2::zero(), time_end, f32::INFINITY),
+        ];
+
+        // The target always ends up standing still, so any pawn that can move catches it eventually.
+        let wait = parts.into_iter().find_map(|(at, velocity, beg, end)| {
+            let (beg, end) = ((beg - now).max(0.0), end - now);
+            (beg <= end).then(|| earliest_reach(at - pos.vec(), velocity, move_speed, reach, beg, end)).flatten()
+        });
+
+        wait.map(|wait| now + wait)
+    }
+}
+
+/// The earliest time in `beg..=end` that a pawn moving at `speed` can get within `reach` of a point
+/// that's `offset` away and moving at `velocity`.
+fn earliest_reach(offset: Vec2, velocity: Vec2, speed: f32, reach: f32, beg: f32, end: f32) -> Option<f32> {
+    // The point is in reach at time `t` when |offset + velocity * t| <= speed * t + reach, which
+    // squared gives a quadratic that's at most zero for the times it's in reach.
+    let a = velocity.dot(velocity) - speed * speed;
+    let b = 2.0 * (offset.dot(velocity) - speed * reach);
+    let c = offset.dot(offset) - reach * reach;
+
+    let in_reach = |t: f32| (a * t + b) * t + c <= 0.0;
+    if in_reach(beg) {
+        return Some(beg);
+    }
+
+    // Otherwise the first root after `beg` is where the point comes into reach. `a` is the difference
+    // of two squared speeds, so when they're about the same it's rounding error of their size, and
+    // the quadratic is really a linear equation.
+    let roots = if a.abs() <= 4.0 * f32::EPSILON * (velocity.dot(velocity) + speed * speed) {
+        [-c / b, f32::NAN]
+    } else {
+        let d = b * b - 4.0 * a * c;
+        [(-b - d.sqrt()) / (2.0 * a), (-b + d.sqrt()) / (2.0 * a)]
+    };
+
+    roots.into_iter().filter(|&t| beg < t && t <= end && t.is_finite()).min_by(f32::total_cmp)
+}
+
+/// A [`SearchProblem`] for catching a [`MovingTarget`] as soon as possible while dodging missiles.
+///
+/// The cost of a move is the time it takes, measured in the time it takes to move `step_size`.
+pub struct Pursuit<'a> {
+    pub target: MovingTarget,
+    pub missiles: &'a MissileSet,
+    pub pawn_size: f32,
+    pub move_speed: f32,
+    /// The amount of units moved in a single step of the search.
+    pub step_size: f32,
+}
+
+impl SearchProblem for Pursuit<'_> {
+    type Node = Pos;
+    type Cost = Cost;
+    type Successors = Vec<(Pos, Cost)>;
+
+    fn successors(&mut self, node: &Pos) -> Self::Successors {
+        let step_time = self.step_size / self.move_speed;
+        node.successors(step_time, self.step_size)
+            .into_iter()
+            .map(|(next, _)| (next, self.movement_cost(node, &next)))
+            .collect()
+    }
+
+    fn is_valid_move(&mut self, from: &Pos, to: &Pos) -> bool {
+        self.missiles.collides_points(from, to, self.move_speed, self.pawn_size).is_none()
+    }
+
+    fn movement_cost(&mut self, from: &Pos, to: &Pos) -> Cost {
+        ((to.time() - from.time()) * self.move_speed / self.step_size).into()
+    }
+
+    fn heuristic(&mut self, node: &Pos) -> Cost {
+        // A target that can't be caught is infinitely far away.
+        let Some(intercept) = self.target.intercept_time(node, self.move_speed, self.pawn_size) else {
+            return f32::INFINITY.into();
+        };
+
+        ((intercept - node.time()) * self.move_speed / self.step_size).into()
+    }
+
+    fn success(&mut self, node: &Pos) -> bool {
+        self.target.caught(node, self.pawn_size)
+    }
+
+    fn jump_check(&mut self, from: &Pos, skip: &Pos, to: &Pos) -> Option<Pos> {
+        Pos::jump_calc(from, skip, to, self.move_speed)
+    }
+}
+
+#[test]
+fn intercept_time_of_stationary_target() {
+    let target = MovingTarget(Missile::new(0.0, Vec2::new(500.0, 0.0), Vec2::new(500.0, 0.0), 0.0, 1.0));
+    assert_eq!(target.intercept_time(&Pos::new(0.0, 0.0, 1.0), 100.0, 0.0), Some(6.0));
+
+    // Reaching out to touch the target is enough.
+    assert_eq!(target.intercept_time(&Pos::new(0.0, 0.0, 1.0), 100.0, 100.0), Some(5.0));
+}
+
+#[test]
+fn intercept_time_of_moving_target() {
+    // Running away, and caught up with once the pawn has made up the distance.
+    let target = MovingTarget(Missile::new(0.0, Vec2::new(100.0, 0.0), Vec2::new(1000.0, 0.0), 0.0, 50.0));
+    assert_eq!(target.intercept_time(&Pos::new(0.0, 0.0, 0.0), 100.0, 0.0), Some(2.0));
+
+    // Running towards the pawn.
+    let target = MovingTarget(Missile::new(0.0, Vec2::new(300.0, 0.0), Vec2::new(-1000.0, 0.0), 0.0, 50.0));
+    assert_eq!(target.intercept_time(&Pos::new(0.0, 0.0, 0.0), 100.0, 0.0), Some(2.0));
+
+    // Waiting at its origin until it spawns.
+    let target = MovingTarget(Missile::new(10.0, Vec2::new(100.0, 0.0), Vec2::new(1000.0, 0.0), 0.0, 50.0));
+    assert_eq!(target.intercept_time(&Pos::new(0.0, 0.0, 0.0), 100.0, 0.0), Some(1.0));
+
+    // Faster than the pawn, so it's only caught once it stops at its target.
+    let target = MovingTarget(Missile::new(0.0, Vec2::new(100.0, 0.0), Vec2::new(1100.0, 0.0), 0.0, 200.0));
+    assert_eq!(target.intercept_time(&Pos::new(0.0, 0.0, 0.0), 100.0, 0.0), Some(11.0));
+
+    // Running diagonally towards the pawn at the same speed, which the rounding of its velocity
+    // makes slightly faster or slower.
+    let dir = Vec2::new(1.0, 1.0).normalized();
+    let target = MovingTarget(Missile::new(0.0, dir * 5000.0, Vec2::zero(), 0.0, 999.9));
+    let time = target.intercept_time(&Pos::new(0.0, 0.0, 0.0), 999.9, 0.0).unwrap();
+    assert!((time - 5000.0 / (2.0 * 999.9)).abs() < 1e-3);
+}
+
+#[test]
+fn intercept_time_of_pawn_that_cant_move() {
+    let target = MovingTarget(Missile::new(0.0, Vec2::new(500.0, 0.0), Vec2::new(500.0, 0.0), 0.0, 1.0));
+    assert_eq!(target.intercept_time(&Pos::new(0.0, 0.0, 1.0), 0.0, 0.0), None);
+    assert_eq!(target.intercept_time(&Pos::new(0.0, 0.0, 1.0), f32::NAN, 0.0), None);
+
+    // Unless it's already in reach.
+    assert_eq!(target.intercept_time(&Pos::new(0.0, 0.0, 1.0), 0.0, 500.0), Some(1.0));
+}
+
+#[test]
+fn pursuit_catches_moving_target() {
+    use crate::pathfind::{Budget, SearchContext};
+    use crate::FxIndexMap;
+
+    let target = MovingTarget(Missile::new(0.0, Vec2::new(0.0, 500.0), Vec2::new(1000.0, 500.0), 20.0, 50.0));
+
+    // A missile crossing the straight line to where the target will be caught.
+    let mut missiles = MissileSet(FxIndexMap::default());
+    missiles.0.insert(0, Missile::new(0.0, Vec2::new(500.0, 0.0), Vec2::new(-500.0, 500.0), 40.0, 200.0));
+
+    let mut pursuit = Pursuit { target, missiles: &missiles, pawn_size: 20.0, move_speed: 100.0, step_size: 50.0 };
+    let start = Pos::new(0.0, 0.0, 0.0);
+    let budget = Budget { max_expansions: Some(10000), ..Budget::default() };
+    let path = SearchContext::new().search(&mut pursuit, start, &budget).unwrap();
+
+    let end = path.nodes.last().unwrap();
+    assert!(target.caught(end, 20.0));
+    assert!(target.intercept_time(&start, 100.0, 20.0).is_some_and(|time| time <= end.time()));
+    assert!(path.nodes.windows(2).all(|w| missiles.collides_points(&w[0], &w[1], 100.0, 20.0).is_none()));
+}
+
+#[test]
+fn pursuit_catches_stationary_target() {
+    use crate::pathfind::{Budget, SearchContext};
+    use crate::FxIndexMap;
+
+    let target = MovingTarget(Missile::new(0.0, Vec2::new(500.0, 0.0), Vec2::new(500.0, 0.0), 20.0, 50.0));
+    assert_eq!(target.0.position(3.0), Vec2::new(500.0, 0.0));
+
+    let missiles = MissileSet(FxIndexMap::default());
+    let mut pursuit = Pursuit { target, missiles: &missiles, pawn_size: 20.0, move_speed: 100.0, step_size: 50.0 };
+    let budget = Budget { max_expansions: Some(10000), ..Budget::default() };
+    let path = SearchContext::new().search(&mut pursuit, Pos::new(0.0, 0.0, 0.0), &budget).unwrap();
+    assert!(target.caught(path.nodes.last().unwrap(), 20.0));
+}
@@ -0,0 +1,627 @@
+//! Incremental replanning for problems where the obstacles change between queries.
+//!
+//! The searches in [`pathfind`](crate::pathfind) always start from scratch. When new missiles show
+//! up every few frames most of the previous search tree is still perfectly valid though, so
+//! throwing all of it away is wasteful. A [`Replanner`] instead holds on to its search tree between
+//! queries, in the spirit of Lifelong Planning A*. When the obstacles change it's told which moves
+//! may be affected, and only repairs the parts of the tree that depend on those moves:
+//!
+//! - Moves in the tree that have become invalid are cut, along with everything that was reached
+//!   through them. The nodes that were cut off are then re-derived from the other moves that were
+//!   offered to them while searching, just like LPA* recalculates a node from its predecessors.
+//! - Moves that were rejected earlier but may have become valid are offered again.
+//!
+//! The search itself works just like the one in `pathfind`, including the lazily validated jumps
+//! and their fallbacks, so refer to the comments in there for the details of how that works. The
+//! main difference is that every move that was ever offered to a node is remembered, instead of
+//! only the best one, since that's what makes it possible to repair the tree later on.
+
+use num_traits::Zero;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::hash::Hash;
+use std::iter;
+
+use crate::pathfind::{Budget, FindError, Path, SearchProblem};
+use crate::FxIndexMap;
+
+#[cfg(test)]
+use crate::pathfind::GridProblem;
+
+/// The parent index of the start node, and of nodes that aren't currently reachable.
+const NO_PARENT: usize = usize::MAX;
+
+/// A search tree that's kept around between queries and repaired when obstacles change.
+///
+/// The start node is fixed for the lifetime of the planner, so a new planner is needed whenever the
+/// start changes.
+pub struct Replanner<N, C> {
+    // All nodes we've seen, along with every move that was offered to them.
+    nodes: FxIndexMap<N, Entry<N, C>>,
+    // All the nodes we've seen but haven't yet validated or expanded.
+    pending: BinaryHeap<Queued<C>>,
+    // The goal found by the latest query, if any. It's put back into the pending heap at the start
+    // of the next query so that it's found again unless something better has turned up.
+    goal: Option<usize>,
+}
+
+struct Entry<N, C> {
+    // Index of the parent of the node in the current tree.
+    parent: usize,
+    // Cost to get to here through the parent, or `None` if the node isn't currently reachable.
+    cost: Option<C>,
+    // Whether the move from the parent to here has been validated.
+    checked: bool,
+    // Whether the node has been expanded since it was last reached, which means other nodes may
+    // have been reached through it.
+    expanded: bool,
+    // Every move that has been offered to this node, at most one per parent.
+    offers: Vec<Offer<N, C>>,
+}
+
+#[derive(Copy, Clone)]
+struct Offer<N, C> {
+    parent: usize,
+    // Unlike in `pathfind` we store the cost of the move itself rather than the total cost, since
+    // the cost of getting to the parent may change when the tree is repaired.
+    move_cost: C,
+    // Jumps carry the normal move they replaced, which is offered if the jump is rejected.
+    fallback: Option<Fallback<N, C>>,
+    // Whether the move was found to be invalid. Rejected offers are never used again, unless
+    // they're reported as changed when the planner is updated.
+    rejected: bool,
+}
+
+#[derive(Copy, Clone)]
+struct Fallback<N, C> {
+    parent: usize,
+    move_cost: C,
+    node: N,
+}
+
+impl<N, C> Replanner<N, C>
+where
+    N: Eq + Hash + Copy,
+    C: Zero + Ord + Copy,
+{
+    #[must_use]
+    pub fn new(start: N) -> Self {
+        let mut nodes = FxIndexMap::default();
+        let root =
+            Entry { parent: NO_PARENT, cost: Some(Zero::zero()), checked: true, expanded: false, offers: Vec::new() };
+        nodes.insert(start, root);
+
+        let mut pending = BinaryHeap::new();
+        pending.push(Queued { estimated_cost: Zero::zero(), cost: Zero::zero(), index: 0 });
+
+        Replanner { nodes, pending, goal: None }
+    }
+
+    #[must_use]
+    pub fn start(&self) -> N {
+        *self.key(0)
+    }
+
+    /// The number of nodes the planner is holding on to.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Continues the search until a goal is found, picking up wherever the previous query left
+    /// off. If nothing changed since the previous query, the previous path is returned without
+    /// doing any more work.
+    ///
+    /// Stopping early because the budget ran out doesn't lose any work, so calling this again
+    /// continues where it stopped.
+    pub fn plan<P>(&mut self, problem: &mut P, budget: &Budget) -> Result<Path<N, C>, FindError>
+    where
+        P: SearchProblem<Node = N, Cost = C>,
+    {
+        if let Some(goal) = self.goal.take() {
+            if let Some(cost) = self.nodes[goal].cost {
+                let estimated_cost = cost + problem.heuristic(self.key(goal));
+                self.pending.push(Queued { estimated_cost, cost, index: goal });
+            }
+        }
+
+        let mut expansions = 0;
+
+        while let Some(queued) = self.pending.pop() {
+            let Queued { cost, index: p0_index, .. } = queued;
+            let (&p0_node, entry) = self.nodes.get_index(p0_index).unwrap();
+
+            // The tree may have changed since this was pushed, either because a cheaper way here
+            // was found or because the node was cut off.
+            if entry.cost != Some(cost) {
+                continue;
+            }
+
+            if !entry.checked {
+                let p1_node = *self.key(entry.parent);
+
+                if !problem.is_valid_move(&p1_node, &p0_node) {
+                    self.reject(problem, p0_index);
+                    continue;
+                }
+
+                self.nodes[p0_index].checked = true;
+            }
+
+            if problem.success(&p0_node) {
+                self.goal = Some(p0_index);
+                return Ok(self.path(p0_index));
+            }
+
+            if let Some(limit) = budget.exceeded(expansions, self.nodes.len()) {
+                // Put the node back so that the next query can pick up from here.
+                self.pending.push(queued);
+                return Err(FindError::BudgetExceeded(limit));
+            }
+
+            expansions += 1;
+            self.expand(problem, p0_index);
+        }
+
+        Err(FindError::Exhausted)
+    }
+
+    /// Repairs the search tree after the obstacles changed.
+    ///
+    /// `changed` is called with moves that have been considered so far, and should return `true`
+    /// for any move whose validity may have changed, in either direction. It's fine for it to be
+    /// conservative, since the moves it reports are validated again rather than trusted, but the
+    /// fewer moves it reports the less work there is to do. With missiles, checking the move
+    /// against only the added and removed missiles is a good fit.
+    ///
+    /// The problem has to be updated to the new obstacles before calling this.
+    pub fn update<P>(&mut self, problem: &mut P, mut changed: impl FnMut(&N, &N) -> bool)
+    where
+        P: SearchProblem<Node = N, Cost = C>,
+    {
+        // Rejected moves that may have become valid, and moves in the tree that have become
+        // invalid, as (node index, offer index) pairs.
+        let mut revived = Vec::new();
+        let mut cut = Vec::new();
+
+        for (index, (node, entry)) in self.nodes.iter().enumerate() {
+            for (offer_index, offer) in entry.offers.iter().enumerate() {
+                let parent = self.key(offer.parent);
+                if !changed(parent, node) {
+                    continue;
+                }
+
+                if offer.rejected {
+                    revived.push((index, offer_index));
+                } else if entry.checked && offer.parent == entry.parent && !problem.is_valid_move(parent, node) {
+                    cut.push((index, offer_index));
+                }
+            }
+        }
+
+        let mut fallbacks = Vec::new();
+        for &(index, offer_index) in &cut {
+            let offer = &mut self.nodes[index].offers[offer_index];
+            offer.rejected = true;
+            fallbacks.extend(offer.fallback);
+        }
+
+        // Detaching below shuffles the offers around, so hold on to the revived moves themselves.
+        let revived = revived
+            .into_iter()
+            .map(|(index, offer_index)| {
+                let offer = &mut self.nodes[index].offers[offer_index];
+                offer.rejected = false;
+                (index, offer.parent, offer.move_cost)
+            })
+            .collect::<Vec<_>>();
+
+        // Cut off everything that was reached through a move that has become invalid.
+        let detached = self.detach(cut.iter().map(|&(index, _)| index));
+
+        // Then try to reach the detached nodes from what's left of the tree instead.
+        for &index in &detached {
+            self.rederive(problem, index);
+        }
+
+        for (index, parent, move_cost) in revived {
+            self.consider(problem, index, parent, move_cost);
+        }
+
+        for fb in fallbacks {
+            self.offer(problem, fb.node, fb.parent, fb.move_cost, None);
+        }
+    }
+
+    /// Marks the current move to a node as invalid, and falls back to the alternatives.
+    fn reject<P>(&mut self, problem: &mut P, index: usize)
+    where
+        P: SearchProblem<Node = N, Cost = C>,
+    {
+        let entry = &mut self.nodes[index];
+        let parent = entry.parent;
+
+        let mut fallback = None;
+        if let Some(offer) = entry.offers.iter_mut().find(|o| o.parent == parent) {
+            offer.rejected = true;
+            fallback = offer.fallback;
+        }
+
+        // A node that was already expanded may have been moved onto this move when it turned out
+        // to be cheaper, in which case everything reached through it has to be cut off as well.
+        let detached = if entry.expanded {
+            self.detach([index])
+        } else {
+            entry.parent = NO_PARENT;
+            entry.cost = None;
+            entry.checked = false;
+            vec![index]
+        };
+
+        for index in detached {
+            self.rederive(problem, index);
+        }
+
+        if let Some(fb) = fallback {
+            self.offer(problem, fb.node, fb.parent, fb.move_cost, None);
+        }
+    }
+
+    /// Makes the cheapest offer that isn't known to be invalid the current move to a node.
+    fn rederive<P>(&mut self, problem: &mut P, index: usize)
+    where
+        P: SearchProblem<Node = N, Cost = C>,
+    {
+        let best = self.nodes[index]
+            .offers
+            .iter()
+            .filter(|offer| !offer.rejected)
+            .filter_map(|offer| Some((self.nodes[offer.parent].cost? + offer.move_cost, offer.parent)))
+            .min_by_key(|&(cost, _)| cost);
+
+        if let Some((cost, parent)) = best {
+            self.set_parent(problem, index, parent, cost);
+        }
+    }
+
+    /// Expands a node the same way `pathfind` does, offering a jump from the parent in place of
+    /// a normal move whenever `jump_check` allows it.
+    fn expand<P>(&mut self, problem: &mut P, p0_index: usize)
+    where
+        P: SearchProblem<Node = N, Cost = C>,
+    {
+        let (&mut p0_node, entry) = self.nodes.get_index_mut(p0_index).unwrap();
+        let p1_index = entry.parent;
+        entry.expanded = true;
+
+        for (mut node, move_cost) in problem.successors(&p0_node) {
+            let (mut parent, mut move_cost, mut fallback) = (p0_index, move_cost, None);
+
+            if let Some((p1_node, _)) = self.nodes.get_index(p1_index) {
+                if let Some(jump_node) = problem.jump_check(p1_node, &p0_node, &node) {
+                    fallback = Some(Fallback { parent: p0_index, move_cost, node });
+                    move_cost = problem.movement_cost(p1_node, &jump_node);
+                    parent = p1_index;
+                    node = jump_node;
+                }
+            }
+
+            self.offer(problem, node, parent, move_cost, fallback);
+        }
+    }
+
+    /// Records a move to `node`, and makes it the current one if it's cheaper.
+    fn offer<P>(&mut self, problem: &mut P, node: N, parent: usize, move_cost: C, fallback: Option<Fallback<N, C>>)
+    where
+        P: SearchProblem<Node = N, Cost = C>,
+    {
+        // Moves from nodes that can't be reached will be offered again once they're expanded.
+        if self.nodes[parent].cost.is_none() {
+            return;
+        }
+
+        let new = || Entry { parent: NO_PARENT, cost: None, checked: false, expanded: false, offers: Vec::new() };
+        let entry = self.nodes.entry(node);
+        let index = entry.index();
+        let entry = entry.or_insert_with(new);
+
+        let offer = Offer { parent, move_cost, fallback, rejected: false };
+        match entry.offers.iter_mut().find(|o| o.parent == parent) {
+            // Offering the same move again can't make a rejected move valid.
+            Some(existing) if existing.rejected => return,
+            Some(existing) => *existing = offer,
+            None => entry.offers.push(offer),
+        }
+
+        self.consider(problem, index, parent, move_cost);
+    }
+
+    /// Makes the move from `parent` the current move to the node at `index` if it's cheaper.
+    fn consider<P>(&mut self, problem: &mut P, index: usize, parent: usize, move_cost: C)
+    where
+        P: SearchProblem<Node = N, Cost = C>,
+    {
+        let Some(parent_cost) = self.nodes[parent].cost else {
+            return;
+        };
+
+        let cost = parent_cost + move_cost;
+        if self.nodes[index].cost.is_none_or(|current| cost < current) {
+            self.set_parent(problem, index, parent, cost);
+        }
+    }
+
+    fn set_parent<P>(&mut self, problem: &mut P, index: usize, parent: usize, cost: C)
+    where
+        P: SearchProblem<Node = N, Cost = C>,
+    {
+        let (node, entry) = self.nodes.get_index_mut(index).unwrap();
+
+        // A move that has already been validated doesn't need to be validated again just because
+        // the cost of getting to its parent went down.
+        entry.checked &= entry.parent == parent;
+        entry.parent = parent;
+        entry.cost = Some(cost);
+
+        let estimated_cost = cost + problem.heuristic(node);
+        self.pending.push(Queued { estimated_cost, cost, index });
+    }
+
+    /// Makes the given nodes and everything reached through them unreachable, and returns all the
+    /// nodes that were affected.
+    fn detach(&mut self, roots: impl IntoIterator<Item = usize>) -> Vec<usize> {
+        let mut children = vec![Vec::new(); self.nodes.len()];
+        for (index, (_, entry)) in self.nodes.iter().enumerate() {
+            if entry.cost.is_some() && entry.parent != NO_PARENT {
+                children[entry.parent].push(index);
+            }
+        }
+
+        let mut detached = vec![false; self.nodes.len()];
+        let mut stack = roots.into_iter().collect::<Vec<_>>();
+        let mut out = Vec::new();
+
+        while let Some(index) = stack.pop() {
+            if std::mem::replace(&mut detached[index], true) {
+                continue;
+            }
+
+            stack.extend(&children[index]);
+            out.push(index);
+
+            let entry = &mut self.nodes[index];
+            entry.parent = NO_PARENT;
+            entry.cost = None;
+            entry.checked = false;
+            entry.expanded = false;
+
+            if self.goal == Some(index) {
+                self.goal = None;
+            }
+        }
+
+        // Moves offered by detached nodes are forgotten, since they'll be offered again if the
+        // nodes are reached and expanded again.
+        for entry in self.nodes.values_mut() {
+            entry.offers.retain(|offer| !detached[offer.parent]);
+        }
+
+        out
+    }
+
+    fn key(&self, index: usize) -> &N {
+        self.nodes.get_index(index).unwrap().0
+    }
+
+    /// Rebuilds the path to the node at `index` by walking the trail of parent indices.
+    fn path(&self, index: usize) -> Path<N, C> {
+        let parent = |&index: &usize| Some(self.nodes[index].parent).filter(|&p| p != NO_PARENT);
+        let mut indices = iter::successors(Some(index), parent).collect::<Vec<_>>();
+        indices.reverse();
+
+        let entry = |i: usize| self.nodes.get_index(i).unwrap();
+        let nodes = indices.iter().map(|&i| *entry(i).0).collect::<Vec<_>>();
+        // Everything on the path is reachable, so it all has a cost.
+        let costs = indices.iter().map(|&i| entry(i).1.cost.unwrap()).collect::<Vec<_>>();
+        // Only jumps carry a fallback, so that's what tells them apart from plain moves.
+        let jump = |entry: &Entry<N, C>| entry.offers.iter().any(|o| o.parent == entry.parent && o.fallback.is_some());
+        let jumps = indices.iter().skip(1).map(|&i| jump(entry(i).1)).collect();
+
+        Path { nodes, cost: *costs.last().unwrap(), partial: false, costs, jumps, indices }
+    }
+}
+
+// The same ordering as `pathfind` uses, minus the fallback data since that's kept with the offers.
+struct Queued<C> {
+    estimated_cost: C,
+    cost: C,
+    index: usize,
+}
+
+impl<C: Eq> Eq for Queued<C> {}
+impl<C: PartialEq> PartialEq for Queued<C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.estimated_cost == other.estimated_cost && self.cost == other.cost
+    }
+}
+
+impl<C: Ord> PartialOrd for Queued<C> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<C: Ord> Ord for Queued<C> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match other.estimated_cost.cmp(&self.estimated_cost) {
+            Ordering::Equal => self.cost.cmp(&other.cost),
+            s => s,
+        }
+    }
+}
+
+#[cfg(test)]
+fn replan_after(planner: &mut Replanner<(i32, i32), crate::Cost>, problem: &mut GridProblem, changed: &[(i32, i32)]) {
+    planner.update(problem, |from, to| GridProblem::cells_between(from, to).any(|cell| changed.contains(&cell)));
+}
+
+#[test]
+fn replan_routes_around_added_walls() {
+    let mut problem = GridProblem::new(10, (8, 0));
+    let mut planner = Replanner::new((-8, 0));
+
+    let path = planner.plan(&mut problem, &Budget::default()).unwrap();
+    assert_eq!(path.cost.0, 16.0);
+    assert_eq!(path.costs.len(), path.nodes.len());
+    assert!(path.jumps.iter().all(|&jump| !jump));
+
+    let wall = (-3..=3).map(|y| (0, y)).collect::<Vec<_>>();
+    problem.blocked.extend(&wall);
+    replan_after(&mut planner, &mut problem, &wall);
+
+    problem.checks = 0;
+    let path = planner.plan(&mut problem, &Budget::default()).unwrap();
+    let repair_checks = problem.checks;
+
+    problem.checks = 0;
+    let fresh = Replanner::new((-8, 0)).plan(&mut problem, &Budget::default()).unwrap();
+
+    assert_eq!(path.cost, fresh.cost);
+    assert!(path.nodes.iter().all(|n| !problem.blocked.contains(n)));
+    assert!(repair_checks < problem.checks);
+}
+
+#[test]
+fn replan_takes_shortcut_after_removing_walls() {
+    let mut problem = GridProblem::new(10, (8, 0));
+    let wall = (-3..=3).map(|y| (0, y)).collect::<Vec<_>>();
+    problem.blocked.extend(&wall);
+
+    let mut planner = Replanner::new((-8, 0));
+    let detour = planner.plan(&mut problem, &Budget::default()).unwrap().cost;
+    assert!(detour.0 > 16.0);
+
+    problem.blocked.clear();
+    replan_after(&mut planner, &mut problem, &wall);
+
+    let cost = planner.plan(&mut problem, &Budget::default()).unwrap().cost;
+    assert_eq!(cost.0, 16.0);
+}
+
+#[test]
+fn replan_keeps_jumps_valid() {
+    let mut problem = GridProblem::new(10, (8, 3)).with_jumps();
+    let mut planner = Replanner::new((-8, -3));
+    let path = planner.plan(&mut problem, &Budget::default()).unwrap();
+    assert_eq!(path.nodes, [(-8, -3), (8, 3)]);
+    assert_eq!(path.jumps, [true]);
+
+    let wall = (-5..=5).map(|y| (0, y)).collect::<Vec<_>>();
+    problem.blocked.extend(&wall);
+    replan_after(&mut planner, &mut problem, &wall);
+
+    let path = planner.plan(&mut problem, &Budget::default()).unwrap();
+    assert!(path.segments().all(|s| problem.is_valid_move(&s.from, &s.to)));
+    assert_eq!(path.nodes.last(), Some(&(8, 3)));
+}
+
+#[test]
+fn replan_resumes_after_running_out_of_budget() {
+    use crate::pathfind::Limit;
+
+    let mut problem = GridProblem::new(10, (8, 0));
+    let mut planner = Replanner::new((-8, 0));
+    let budget = Budget { max_expansions: Some(5), ..Budget::default() };
+
+    let mut limited = 0;
+    let found = loop {
+        match planner.plan(&mut problem, &budget) {
+            Ok(found) => break found,
+            Err(error) => assert_eq!(error, FindError::BudgetExceeded(Limit::Expansions)),
+        }
+        limited += 1;
+    };
+
+    assert!(limited > 0);
+    assert_eq!(found.cost.0, 16.0);
+}
+
+#[cfg(test)]
+#[derive(Default)]
+struct Script {
+    // Moves as (from, to, cost).
+    moves: Vec<(char, char, f32)>,
+    // Jumps as (from, via, to, jump node, cost), offered when expanding `via` with `from` as its
+    // parent in place of the move to `to`.
+    jumps: Vec<(char, char, char, char, f32)>,
+    heuristics: Vec<(char, f32)>,
+    invalid: Vec<(char, char)>,
+    goal: char,
+}
+
+#[cfg(test)]
+impl SearchProblem for Script {
+    type Node = char;
+    type Cost = crate::Cost;
+    type Successors = Vec<(char, crate::Cost)>;
+
+    fn successors(&mut self, node: &char) -> Self::Successors {
+        self.moves.iter().filter(|m| m.0 == *node).map(|m| (m.1, m.2.into())).collect()
+    }
+
+    fn is_valid_move(&mut self, from: &char, to: &char) -> bool {
+        !self.invalid.contains(&(*from, *to))
+    }
+
+    fn movement_cost(&mut self, from: &char, to: &char) -> crate::Cost {
+        self.jumps.iter().find(|j| j.0 == *from && j.3 == *to).unwrap().4.into()
+    }
+
+    fn heuristic(&mut self, node: &char) -> crate::Cost {
+        self.heuristics.iter().find(|h| h.0 == *node).map_or(0.0, |h| h.1).into()
+    }
+
+    fn success(&mut self, node: &char) -> bool {
+        *node == self.goal
+    }
+
+    fn jump_check(&mut self, from: &char, via: &char, to: &char) -> Option<char> {
+        self.jumps.iter().find(|j| (j.0, j.1, j.2) == (*from, *via, *to)).map(|j| j.3)
+    }
+}
+
+#[test]
+fn replan_detaches_expanded_node_behind_rejected_jump() {
+    let mut problem = Script {
+        moves: vec![
+            ('S', 'P', 1.0),
+            ('S', 'A', 1.2),
+            ('P', 'X', 1.0),
+            ('X', 'G', 5.0),
+            ('A', 'B', 1.0),
+            ('B', 'Y', 1.0),
+            ('B', 'D', 0.1),
+        ],
+        jumps: vec![('A', 'B', 'Y', 'X', 0.3)],
+        heuristics: vec![('X', 1.0), ('B', 1.0)],
+        invalid: vec![('A', 'X')],
+        goal: 'G',
+    };
+
+    // X is expanded through P, and the goal is reached through it. Expanding B then offers a
+    // cheaper jump to X, and the search stops before that jump gets validated.
+    let mut planner = Replanner::new('S');
+    let budget = Budget { max_expansions: Some(5), ..Budget::default() };
+    assert!(planner.plan(&mut problem, &budget).is_err());
+
+    // Once the move from P is blocked as well, there's no way to X and thus none to the goal. The
+    // goal mustn't be reached through X anyway because it was reached before X was moved.
+    problem.invalid.push(('P', 'X'));
+    planner.update(&mut problem, |from, to| (*from, *to) == ('P', 'X'));
+    assert_eq!(planner.plan(&mut problem, &Budget::default()), Err(FindError::Exhausted));
+}
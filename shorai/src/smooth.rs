@@ -0,0 +1,104 @@
+//! Post-processing that removes redundant corners from a found path.
+//!
+//! The jumps in the search only skip a limited number of ancestors and give up at the first one
+//! they can't skip, so paths still tend to contain corners that a straight line would have avoided,
+//! especially after the search had to go around something. [`smooth_path`] goes over a finished
+//! path and greedily replaces runs of nodes with a single move wherever that move is valid.
+
+#[cfg(test)]
+use crate::pathfind::GridProblem;
+
+/// Shortcuts a path between non-adjacent nodes wherever possible.
+///
+/// Starting from the first node, this looks for the farthest node that can be moved to directly,
+/// moves there, and repeats from that node until the end of the path is reached. The arguments
+/// are the same callbacks the search takes, see the [`pathfind`](crate::pathfind) module docs:
+///
+/// - `is_valid_move` decides whether a shortcut is allowed.
+/// - `jump_check` is called with the node the shortcut starts from, the last node it skips over,
+///   and the node it ends at. It can reject a shortcut by returning `None`, or adjust the node it
+///   ends at, such as recalculating the time it's reached with [`Pos::jump_calc`].
+///
+/// Since adjusting the end of a shortcut may change the move out of it as well, a shortcut is only
+/// taken if the move from its end to the next node in the original path is also valid. The first
+/// and last nodes are always kept.
+///
+/// Only the end of a shortcut is adjusted. The nodes after it are kept as they were, so with
+/// [`Pos`](crate::pos::Pos) a pawn that gets to the end of a shortcut early still reaches the
+/// nodes after it at their original times, waiting at them until then.
+///
+/// This calls `is_valid_move` up to twice for every pair of nodes in the worst case, so it's meant
+/// for the relatively short paths the search returns rather than raw grid paths.
+///
+/// [`Pos::jump_calc`]: crate::pos::Pos::jump_calc
+pub fn smooth_path<N, V, J>(path: &[N], mut is_valid_move: V, mut jump_check: J) -> Vec<N>
+where
+    N: Copy,
+    V: FnMut(&N, &N) -> bool,
+    J: FnMut(&N, &N, &N) -> Option<N>,
+{
+    let Some(&first) = path.first() else {
+        return Vec::new();
+    };
+
+    let mut smoothed = vec![first];
+    let mut index = 0;
+
+    while index + 1 < path.len() {
+        let from = *smoothed.last().unwrap();
+
+        let shortcut = (index + 2..path.len()).rev().find_map(|to_index| {
+            let to = jump_check(&from, &path[to_index - 1], &path[to_index])?;
+            let valid =
+                is_valid_move(&from, &to) && path.get(to_index + 1).is_none_or(|after| is_valid_move(&to, after));
+            valid.then_some((to_index, to))
+        });
+
+        // If no shortcut was found, the next node is always reachable. Either because it's the
+        // original move, or because it was checked when taking the shortcut that got us here.
+        let (next_index, next) = shortcut.unwrap_or((index + 1, path[index + 1]));
+        smoothed.push(next);
+        index = next_index;
+    }
+
+    smoothed
+}
+
+#[cfg(test)]
+fn clear_of(walls: &[(i32, i32)]) -> impl FnMut(&(i32, i32), &(i32, i32)) -> bool + '_ {
+    move |from, to| GridProblem::cells_between(from, to).all(|cell| !walls.contains(&cell))
+}
+
+#[test]
+fn smooth_path_removes_redundant_corners() {
+    let path = [(0, 0), (1, 0), (2, 0), (3, 0), (3, 1), (3, 2), (3, 3)];
+    let smoothed = smooth_path(&path, clear_of(&[]), |_, _, &to| Some(to));
+
+    assert_eq!(smoothed, [(0, 0), (3, 3)]);
+}
+
+#[test]
+fn smooth_path_keeps_corners_around_walls() {
+    let path = [(0, 0), (1, 0), (2, 0), (3, 0), (3, 1), (3, 2), (3, 3)];
+    let smoothed = smooth_path(&path, clear_of(&[(1, 1), (2, 2), (2, 1)]), |_, _, &to| Some(to));
+
+    assert_eq!(smoothed, [(0, 0), (3, 0), (3, 3)]);
+}
+
+#[test]
+fn smooth_path_respects_jump_check() {
+    let path = [(0, 0), (1, 0), (2, 0), (2, 1)];
+    let smoothed = smooth_path(&path, clear_of(&[]), |_, _, _| None);
+
+    assert_eq!(smoothed, path);
+}
+
+#[test]
+fn smooth_path_recalculates_time() {
+    use crate::pos::Pos;
+
+    let path = [Pos::new(0.0, 0.0, 0.0), Pos::new(3.0, 0.0, 3.0), Pos::new(3.0, 4.0, 7.0)];
+    let smoothed = smooth_path(&path, |_, _| true, |n1, n2, to| Pos::jump_calc(n1, n2, to, 1.0));
+
+    assert_eq!(smoothed, [Pos::new(0.0, 0.0, 0.0), Pos::new(3.0, 4.0, 5.0)]);
+}